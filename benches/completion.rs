@@ -0,0 +1,106 @@
+/*
+ * Copyright (c) 2021 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Benchmarks of the completion model updates when typing in the command entry.
+//! Run with `cargo bench`: a display is needed to initialize GTK.
+
+extern crate gtk;
+extern crate mg;
+
+use std::collections::HashMap;
+use std::env;
+use std::time::{Duration, Instant};
+
+use mg::completion::{Completer, Completers, Completion, CompletionResult, fuzzy_completions};
+
+const CANDIDATE_COUNT: usize = 5000;
+const INPUTS: &[&str] = &["", "o", "op", "ope", "open", "open-", "open-t", "open-ta", "open-tab"];
+const ITERATIONS: u32 = 10;
+
+struct ListCompleter {
+    candidates: Vec<(String, String)>,
+}
+
+impl ListCompleter {
+    fn new() -> Self {
+        let names = ["open", "close", "reload", "bookmark", "search", "download"];
+        let candidates = (0..CANDIDATE_COUNT)
+            .map(|index| {
+                let name = format!("{}-tab-{}", names[index % names.len()], index);
+                let description = format!("Description of the candidate number {}", index);
+                (name, description)
+            })
+            .collect();
+        ListCompleter {
+            candidates,
+        }
+    }
+}
+
+impl Completer for ListCompleter {
+    fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
+        fuzzy_completions(input, self.candidates.iter()
+            .map(|(name, description)| vec![name.as_str(), description.as_str()]))
+    }
+}
+
+fn new_completion() -> Completion {
+    let mut completers: Completers = HashMap::new();
+    completers.insert("list", Box::new(ListCompleter::new()));
+    let mut completion = Completion::new();
+    completion.set_completers(completers);
+    completion.adjust_model("list");
+    completion
+}
+
+/// Time the filtering of every input, as if the user was typing them.
+/// When `rebuild` is true, a new model is created for every input.
+fn bench(name: &str, page_size: usize, rebuild: bool) {
+    let mut completion = new_completion();
+    completion.set_page_size(page_size);
+    let mut total = Duration::new(0, 0);
+    for _ in 0..ITERATIONS {
+        for input in INPUTS {
+            let start = Instant::now();
+            if rebuild {
+                // Switching completer forces the creation of a new model.
+                completion.adjust_model("");
+                completion.adjust_model("list");
+            }
+            completion.filter(input);
+            total += start.elapsed();
+        }
+    }
+    let keystrokes = ITERATIONS * INPUTS.len() as u32;
+    println!("{:<40} {:>10.3} ms/keystroke", name, total.as_secs_f64() * 1000.0 / keystrokes as f64);
+}
+
+fn main() {
+    // NOTE: only run when called by `cargo bench`, not when the benchmarks are built by `cargo test`.
+    if !env::args().any(|arg| arg == "--bench") {
+        return;
+    }
+    gtk::init().expect("failed to initialize GTK");
+    bench("rebuild model, all results", usize::MAX, true);
+    bench("incremental model, all results", usize::MAX, false);
+    bench("rebuild model, 200 results per page", 200, true);
+    bench("incremental model, 200 results per page", 200, false);
+}
@@ -37,6 +37,10 @@ use mg::{
     Alert,
     AppClose,
     Color,
+    ConfigLayer,
+    ConfigLayers,
+    Context,
+    Contexts,
     CustomCommand,
     DarkTheme,
     DeleteCompletionItem,
@@ -50,15 +54,18 @@ use mg::{
     SetMode,
     SetSetting,
     SettingChanged,
+    SettingDefaults,
     StatusBarItem,
     StatusBarVisible,
     Title,
+    UpdateDelay,
     Variables,
     Warning,
     blocking_dialog,
     input,
     question,
 };
+use mg_settings::Value;
 use relm::{Relm, Widget};
 use relm_derive::widget;
 
@@ -171,9 +178,14 @@ impl Widget for Win {
     view! {
         #[name="mg"]
         Mg<AppCommand, AppSettings>(MODES, Ok("examples/main.conf".into()), Some("/home/bouanto".into()), vec![]) {
+            ConfigLayers: vec![ConfigLayer::user(), ConfigLayer::file("project", ".mg.conf")],
+            Contexts: vec![("entry", Context::WidgetName("entry".to_string()))],
             DarkTheme: true,
+            SettingDefaults: vec![("boolean", Value::Bool(false)), ("title-len", Value::Int(0)),
+                ("width", Value::Int(0))],
             StatusBarVisible: self.model.statusbar_visible,
             Title: self.model.title.clone(),
+            UpdateDelay: 50,
             Variables: vec![("url", Box::new(|| "http://duckduckgo.com/lite".to_string()))],
             gtk::Box {
                 orientation: Vertical,
@@ -182,6 +194,7 @@ impl Widget for Win {
                 },
                 #[name="entry"]
                 gtk::Entry {
+                    widget_name: "entry",
                 },
                 gtk::Button {
                     label: "Alert",
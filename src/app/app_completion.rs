@@ -21,35 +21,196 @@
 
 use std::collections::HashMap;
 
-use mg_settings::{EnumFromStr, EnumMetaData, SettingCompletion, SpecialCommand};
+use mg_settings::{EnumFromStr, EnumMetaData, SettingCompletion, SpecialCommand, Value};
 use mg_settings::settings;
 
-use app::Mg;
+use app::{
+    Mg,
+    Mode,
+    ModesHash,
+    ERRORS_COMMAND,
+    MESSAGES_COMMAND,
+    MKSETTINGS_COMMAND,
+    SOURCE_COMMAND,
+    WRITE_CONFIG_COMMAND,
+};
+use app::config::{APPLICATION_COMMANDS, create_parser};
+use app::settings::value_to_string;
 use completion::{
     self,
     CommandCompleter,
+    ErrorCompleter,
+    MappingCompleter,
+    MessageCompleter,
+    PathCompleter,
     SettingCompleter,
     DEFAULT_COMPLETER_IDENT,
+    NO_COMPLETER_IDENT,
+    argument_at,
 };
 use completion::completion_view::Msg::{
+    AddCompleter,
+    AddCompleters,
     DeleteCurrentCompletionItem,
     ShowCompletion,
     UpdateCompletions,
 };
 
+/// The identifier of the completer of the actions of the map commands.
+const ACTION_COMPLETER_IDENT: &str = "__mg_action";
+
+/// The identifier of the completer of the config errors.
+const ERRORS_COMPLETER_IDENT: &str = "__mg_errors";
+
+/// The identifier of the completer of the message history.
+const MESSAGES_COMPLETER_IDENT: &str = "__mg_messages";
+
+/// The identifier of the completer of the paths of the config files.
+const PATH_COMPLETER_IDENT: &str = "__mg_path";
+
+/// The map and unmap commands of a mode.
+pub struct MappingCommands {
+    map: String,
+    mode: &'static str,
+    unmap: String,
+}
+
+/// Get the map and unmap commands of every mode.
+pub fn mapping_commands(modes: &ModesHash) -> Vec<MappingCommands> {
+    modes.values()
+        .map(|mode| MappingCommands {
+            map: format!("{}map", mode.prefix),
+            mode: mode.name,
+            unmap: format!("{}unmap", mode.prefix),
+        })
+        .collect()
+}
+
 impl<COMM, SETT> Mg<COMM, SETT>
 where COMM: Clone + EnumFromStr + EnumMetaData + SpecialCommand + 'static,
       SETT: Default + EnumMetaData + settings::Settings + SettingCompletion + 'static,
 {
-    /// Get the default completers.
-    /// One to complete the commands, the other to complete the settings.
+    /// Get the default completers, knowing only the built-in modes.
+    /// One to complete the commands, one to complete the settings, one to complete the actions
+    /// of the map commands and one to complete the paths of config files.
     pub fn default_completers() -> completion::Completers {
+        let (_, modes) = create_parser::<COMM>(&[], None);
+        Self::default_mode_completers(&modes)
+    }
+
+    /// Get the default completers, completing the map commands of these modes.
+    pub fn default_mode_completers(modes: &ModesHash) -> completion::Completers {
         let mut completers: HashMap<_, Box<dyn completion::Completer>> = HashMap::new();
-        completers.insert(DEFAULT_COMPLETER_IDENT, Box::new(CommandCompleter::<COMM>::new()));
-        completers.insert("set", Box::new(SettingCompleter::<SETT>::new()));
+        completers.insert(DEFAULT_COMPLETER_IDENT, Box::new(CommandCompleter::<COMM>::new().modes(modes.values())));
+        completers.insert("set", Box::new(SettingCompleter::<SETT>::new().types()));
+        let action_completer = CommandCompleter::<COMM>::new()
+            .modes(modes.values())
+            .application_commands(APPLICATION_COMMANDS);
+        completers.insert(ACTION_COMPLETER_IDENT, Box::new(action_completer));
+        completers.insert(PATH_COMPLETER_IDENT, Box::new(PathCompleter::new()));
         completers
     }
 
+    /// Get the argument completers of the built-in commands.
+    /// For the map commands, the keys are not completed, but the action is, also after a context
+    /// option since the options are not counted as arguments.
+    pub fn default_argument_completers(&self) -> completion::ArgumentCompleters {
+        let mut argument_completers: completion::ArgumentCompleters = self.model.mapping_commands.iter()
+            .map(|commands| (commands.map.clone().into(), vec![NO_COMPLETER_IDENT, ACTION_COMPLETER_IDENT]))
+            .collect();
+        for &command in &[MKSETTINGS_COMMAND, SOURCE_COMMAND, WRITE_CONFIG_COMMAND] {
+            argument_completers.push((command.into(), vec![PATH_COMPLETER_IDENT]));
+        }
+        argument_completers.push((ERRORS_COMMAND.into(), vec![ERRORS_COMPLETER_IDENT]));
+        argument_completers.push((MESSAGES_COMMAND.into(), vec![MESSAGES_COMPLETER_IDENT]));
+        argument_completers
+    }
+
+    /// Set the default values of the settings, shown in the setting completer.
+    pub fn set_setting_defaults(&mut self, defaults: Vec<(&'static str, Value)>) {
+        self.model.setting_defaults = defaults.iter()
+            .map(|(name, value)| (name.to_string(), value_to_string(value)))
+            .collect();
+        self.model.settings_changed = true;
+    }
+
+    /// Update the setting completer with the current values of the settings, if they changed.
+    pub fn update_setting_completer(&mut self) {
+        if !self.model.settings_changed {
+            return;
+        }
+        self.model.settings_changed = false;
+        let completer = SettingCompleter::<SETT>::new()
+            .types()
+            .current_values(self.model.setting_values.clone())
+            .default_values(self.model.setting_defaults.clone())
+            .unknown_values(self.model.unknown_setting_values.clone());
+        let mut completers: completion::Completers = HashMap::new();
+        completers.insert("set", Box::new(completer));
+        self.model.completion_view.emit(AddCompleters(completers));
+    }
+
+    /// Update the completer of the errors command with the current config errors, if they changed.
+    pub fn update_error_completer(&mut self) {
+        if !self.model.config_errors_changed {
+            return;
+        }
+        self.model.config_errors_changed = false;
+        let errors = self.model.config_errors.iter()
+            .map(|error| (error.location(), error.message.clone()))
+            .collect();
+        let mut completers: completion::Completers = HashMap::new();
+        completers.insert(ERRORS_COMPLETER_IDENT, Box::new(ErrorCompleter::new(errors)));
+        self.model.completion_view.emit(AddCompleters(completers));
+    }
+
+    /// Update the completer of the messages command with the message history, if it changed.
+    pub fn update_message_completer(&mut self) {
+        if !self.model.messages_changed {
+            return;
+        }
+        self.model.messages_changed = false;
+        let messages = self.model.message_history.iter()
+            .rev()
+            .map(|message| (message.time_string(), message.level.to_string(), message.text.clone()))
+            .collect();
+        let mut completers: completion::Completers = HashMap::new();
+        completers.insert(MESSAGES_COMPLETER_IDENT, Box::new(MessageCompleter::new(messages)));
+        self.model.completion_view.emit(AddCompleters(completers));
+    }
+
+    /// Update the completers of the unmap commands with the current key bindings, if they
+    /// changed.
+    pub fn update_mapping_completers(&mut self) {
+        if !self.model.mappings_changed {
+            return;
+        }
+        self.model.mappings_changed = false;
+        for commands in &self.model.mapping_commands {
+            let contexts = self.model.context_mappings.iter()
+                .map(|(context, mappings)| (Some(context), mappings));
+            let mut bindings = vec![];
+            for (context, mappings) in Some((None, &self.model.mappings)).into_iter().chain(contexts) {
+                for (keys, action) in mappings.get(commands.mode).into_iter().flatten() {
+                    let origin_key = (commands.mode, context.cloned(), keys.clone());
+                    let mut action =
+                        match context {
+                            Some(context) => format!("{} (in context {})", action, context),
+                            None => action.clone(),
+                        };
+                    if let Some(origin) = self.model.mapping_origins.get(&origin_key) {
+                        action.push_str(&format!(" (from {})", origin));
+                    }
+                    let keys: String = keys.iter().map(ToString::to_string).collect();
+                    bindings.push((keys, action));
+                }
+            }
+            let completer = MappingCompleter::new(bindings).command(&commands.unmap);
+            // The unmap commands of the user modes are not static.
+            self.model.completion_view.emit(AddCompleter(commands.unmap.clone().into(), Box::new(completer)));
+        }
+    }
+
     /// Delete the current completion item.
     pub fn delete_current_completion_item(&self) {
         self.model.completion_view.emit(DeleteCurrentCompletionItem);
@@ -61,9 +222,24 @@ where COMM: Clone + EnumFromStr + EnumMetaData + SpecialCommand + 'static,
         self.update_completions();
     }
 
+    /// Update the completions when the cursor of the command entry moves to another argument.
+    pub fn cursor_moved(&mut self, position: usize) {
+        let input = &self.model.status_bar_command;
+        let previous_position = self.model.status_bar_cursor.unwrap_or_else(|| input.chars().count());
+        let previous_argument = argument_at(input, previous_position).map(|argument| argument.index);
+        self.model.status_bar_cursor = Some(position);
+        let argument = argument_at(input, position).map(|argument| argument.index);
+        if self.model.current_mode.get() == Mode::Command && previous_argument != argument {
+            self.update_completions();
+        }
+        self.update_suggestion();
+    }
+
     /// Update the items of the completion view.
     pub fn update_completions(&self) {
         let input = self.model.status_bar_command.clone();
-        self.model.completion_view.emit(UpdateCompletions(self.model.mode_string.clone(), input, self.is_normal_command()));
+        let cursor = self.model.status_bar_cursor.unwrap_or_else(|| input.chars().count());
+        self.model.completion_view.emit(UpdateCompletions(self.model.mode_string.clone(), input, cursor,
+            self.is_normal_command()));
     }
 }
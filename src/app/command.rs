@@ -19,7 +19,7 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 use mg_settings::{
     self,
@@ -38,9 +38,14 @@ use app::{
     Mg,
     Mode,
     COMPLETE_NEXT_COMMAND,
+    COMPLETE_PREFIX_COMMAND,
     COMPLETE_PREVIOUS_COMMAND,
+    COMPLETION_INVERT_MARKS,
+    COMPLETION_MARK_ALL,
+    COMPLETION_TOGGLE_MARK,
     COPY,
     CUT,
+    ENTRY_ACCEPT_SUGGESTION,
     ENTRY_DELETE_NEXT_CHAR,
     ENTRY_DELETE_NEXT_WORD,
     ENTRY_DELETE_PREVIOUS_WORD,
@@ -50,14 +55,23 @@ use app::{
     ENTRY_PREVIOUS_CHAR,
     ENTRY_PREVIOUS_WORD,
     ENTRY_SMART_HOME,
+    ERRORS_COMMAND,
+    MESSAGES_COMMAND,
+    MKSETTINGS_COMMAND,
     PASTE,
     PASTE_SELECTION,
+    SOURCE_COMMAND,
+    WRITE_CONFIG_COMMAND,
 };
 use app::ActivationType::{self, Final};
+use app::context::parse_context_mapping;
+use app::set_command::parse_set_form;
 use app::Msg::{
     self,
     CustomCommand,
     EnterNormalModeAndReset,
+    ShowConfigErrors,
+    ShowMessages,
 };
 use app::status_bar::Msg::{
     Copy,
@@ -75,7 +89,16 @@ use app::status_bar::Msg::{
     SmartHome,
 };
 use app::ShortcutCommand::{self, Complete, Incomplete};
-use completion::completion_view::Msg::{SelectNext, SelectPrevious};
+use completion::completion_view::Msg::{
+    AcceptCompletion,
+    CompletePrefix,
+    InvertMarks,
+    MarkAll,
+    SelectNext,
+    SelectPrevious,
+    ToggleMark,
+};
+use file::expand_home;
 
 impl<COMM, SETT> Mg<COMM, SETT>
     where COMM: Clone + EnumFromStr + EnumMetaData + SpecialCommand + 'static,
@@ -97,12 +120,29 @@ impl<COMM, SETT> Mg<COMM, SETT>
     }
 
     /// Handle an application command.
-    fn app_command(&self, command: &str) {
+    fn app_command(&mut self, command: &str) {
         match command {
-            COMPLETE_NEXT_COMMAND => self.model.completion_view.emit(SelectNext),
-            COMPLETE_PREVIOUS_COMMAND => self.model.completion_view.emit(SelectPrevious),
+            COMPLETE_NEXT_COMMAND => {
+                self.flush_pending_updates();
+                self.model.completion_view.emit(SelectNext);
+            },
+            COMPLETE_PREFIX_COMMAND => {
+                self.flush_pending_updates();
+                self.model.completion_view.emit(CompletePrefix);
+            },
+            COMPLETE_PREVIOUS_COMMAND => {
+                self.flush_pending_updates();
+                self.model.completion_view.emit(SelectPrevious);
+            },
+            COMPLETION_INVERT_MARKS => self.model.completion_view.emit(InvertMarks),
+            COMPLETION_MARK_ALL => self.model.completion_view.emit(MarkAll),
+            COMPLETION_TOGGLE_MARK => {
+                self.flush_pending_updates();
+                self.model.completion_view.emit(ToggleMark);
+            },
             COPY => self.streams.status_bar.emit(Copy),
             CUT => self.streams.status_bar.emit(Cut),
+            ENTRY_ACCEPT_SUGGESTION => self.accept_suggestion(),
             ENTRY_DELETE_NEXT_CHAR => self.streams.status_bar.emit(DeleteNextChar),
             ENTRY_DELETE_NEXT_WORD => self.streams.status_bar.emit(DeleteNextWord),
             ENTRY_DELETE_PREVIOUS_WORD => self.streams.status_bar.emit(DeletePreviousWord),
@@ -124,30 +164,30 @@ impl<COMM, SETT> Mg<COMM, SETT>
             App(command) => self.app_command(&command),
             Custom(command) => self.model.relm.stream().emit(CustomCommand(command)),
             Map { action, keys, mode } => {
-                let mode_mappings = self.model.mappings.entry(self.model.modes[mode.as_str()].name)
-                    .or_insert_with(HashMap::new);
-                mode_mappings.insert(keys, action);
-            },
-            Set(name, value) => {
-                match SETT::to_variant(&name, value) {
-                    Ok(setting) => self.set_setting(setting),
-                    Err(error) => {
-                        self.error(Error::Msg("Error setting value".to_string()));
-                        error!("{}", error);
+                match parse_context_mapping(keys, action) {
+                    Ok((context, keys, action)) => {
+                        self.record_mapping_override(&mode, context.as_deref(), &keys, Some(&action));
+                        self.add_mapping(&mode, context, keys, action)
                     },
+                    Err(error) => self.error(error),
                 }
+            },
+            Set(name, value) => {
+                self.set_setting_value(name, value);
                 self.return_to_normal_mode();
             },
             Unmap { keys, mode } => {
-                let mode_mappings = self.model.mappings.entry(self.model.modes[mode.as_str()].name)
-                    .or_insert_with(HashMap::new);
-                mode_mappings.remove(&keys);
+                self.record_mapping_override(&mode, None, &keys, None);
+                self.remove_mapping(&mode, &keys)
             },
         }
     }
 
     /// Handle the command entry activate event.
     pub fn command_activate(&mut self, input: String) {
+        // The activation is not delayed: the pending updates are for an input that is now final.
+        self.cancel_pending_updates();
+        self.model.completion_view.emit(AcceptCompletion(input.clone()));
         let current_mode = self.model.current_mode.get();
         let message =
             if current_mode == Mode::Input || current_mode == Mode::BlockingInput {
@@ -166,6 +206,9 @@ impl<COMM, SETT> Mg<COMM, SETT>
                 }
             }
             else {
+                if current_mode == Mode::Command && self.is_normal_command() {
+                    self.add_to_history(&input);
+                }
                 self.handle_command(input, true, None)
             };
         if let Some(message) = message {
@@ -191,6 +234,12 @@ impl<COMM, SETT> Mg<COMM, SETT>
         -> Option<Msg<COMM, SETT>>
     {
         if self.is_normal_command() || !activated {
+            if self.builtin_command(&command) {
+                if activated {
+                    self.return_to_normal_mode();
+                }
+                return None;
+            }
             let parse_result = self.model.settings_parser.parse_line(&command, prefix);
             self.execute_commands(parse_result, activated);
         }
@@ -203,6 +252,47 @@ impl<COMM, SETT> Mg<COMM, SETT>
         None
     }
 
+    /// Execute the commands handled by Mg which take arguments, since the config parser does not
+    /// support them.
+    /// Return false if the command is not one of them.
+    fn builtin_command(&mut self, command: &str) -> bool {
+        if let Some((name, form)) = parse_set_form(command) {
+            self.set_form(name, form);
+            return true;
+        }
+        let mut words = command.trim().splitn(2, char::is_whitespace);
+        let name = words.next().unwrap_or_default();
+        let argument = words.next().map(str::trim).filter(|argument| !argument.is_empty());
+        match name {
+            ERRORS_COMMAND =>
+                match argument {
+                    Some(number) => self.show_config_error(number),
+                    // Shown after returning to the normal mode.
+                    None => self.model.relm.stream().emit(ShowConfigErrors),
+                },
+            MESSAGES_COMMAND =>
+                match argument {
+                    Some(number) => self.show_logged_message(number),
+                    // Shown after returning to the normal mode.
+                    None => self.model.relm.stream().emit(ShowMessages),
+                },
+            MKSETTINGS_COMMAND | WRITE_CONFIG_COMMAND => self.write_config(argument.map(PathBuf::from)),
+            SOURCE_COMMAND =>
+                match argument {
+                    Some(path) => {
+                        let first_error = self.model.config_errors.len();
+                        self.source_runtime_file(Path::new(&expand_home(path)), path);
+                        if self.report_config_errors(first_error) {
+                            self.info(&format!("Sourced {}", path));
+                        }
+                    },
+                    None => self.error(Error::Msg("Argument required".to_string())),
+                },
+            _ => return false,
+        }
+        true
+    }
+
     /// Handle a special command activate or key press event.
     pub fn handle_special_command(&mut self, activation_type: ActivationType, command: &str) -> Option<Msg<COMM, SETT>> {
         if let Ok(special_command) = COMM::identifier_to_command(self.model.current_command_mode, command) {
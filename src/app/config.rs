@@ -21,7 +21,7 @@
 
 use std::collections::HashMap;
 use std::fs::{File, create_dir_all};
-use std::io::{self, BufReader, Write};
+use std::io::{self, BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 
 use mg_settings::{Config, EnumFromStr, Parser, ParseResult};
@@ -33,9 +33,14 @@ use super::{
     ModesHash,
     COMMAND_MODE,
     COMPLETE_NEXT_COMMAND,
+    COMPLETE_PREFIX_COMMAND,
     COMPLETE_PREVIOUS_COMMAND,
+    COMPLETION_INVERT_MARKS,
+    COMPLETION_MARK_ALL,
+    COMPLETION_TOGGLE_MARK,
     COPY,
     CUT,
+    ENTRY_ACCEPT_SUGGESTION,
     ENTRY_DELETE_NEXT_CHAR,
     ENTRY_DELETE_NEXT_WORD,
     ENTRY_DELETE_PREVIOUS_WORD,
@@ -50,6 +55,13 @@ use super::{
     PASTE_SELECTION,
 };
 
+/// The commands handled by the application, which can be used in mappings.
+pub const APPLICATION_COMMANDS: &[&str] = &[COMPLETE_NEXT_COMMAND, COMPLETE_PREFIX_COMMAND,
+    COMPLETE_PREVIOUS_COMMAND, COMPLETION_INVERT_MARKS, COMPLETION_MARK_ALL, COMPLETION_TOGGLE_MARK, COPY, CUT,
+    ENTRY_ACCEPT_SUGGESTION, ENTRY_DELETE_NEXT_CHAR, ENTRY_DELETE_NEXT_WORD, ENTRY_DELETE_PREVIOUS_WORD, ENTRY_END,
+    ENTRY_NEXT_CHAR, ENTRY_NEXT_WORD, ENTRY_PREVIOUS_CHAR, ENTRY_PREVIOUS_WORD, ENTRY_SMART_HOME, PASTE,
+    PASTE_SELECTION];
+
 /// Create the default config directories and files.
 pub fn create_default_config(default_config: Vec<DefaultConfig>) -> Result<(), io::Error> {
     for config_item in default_config {
@@ -70,12 +82,8 @@ pub fn create_default_config_file(path: &Path, content: &'static str) -> Result<
     Ok(())
 }
 
-/// Parse a configuration file.
-pub fn parse_config<P: AsRef<Path>, COMM: EnumFromStr>(filename: P, user_modes: Modes, include_path: Option<PathBuf>)
-    -> (Parser<COMM>, ParseResult<COMM>, ModesHash)
-{
-    let mut parse_result = ParseResult::new();
-
+/// Create the config parser knowing the user modes and the built-in modes.
+pub fn create_parser<COMM: EnumFromStr>(user_modes: Modes, include_path: Option<PathBuf>) -> (Parser<COMM>, ModesHash) {
     let mut modes = HashMap::new();
     for mode in user_modes {
         modes.insert(mode.prefix, mode.clone());
@@ -85,15 +93,22 @@ pub fn parse_config<P: AsRef<Path>, COMM: EnumFromStr>(filename: P, user_modes:
     assert!(modes.insert("c", Mode { name: COMMAND_MODE, prefix: "c", show_count: false }).is_none(),
         "Duplicate mode prefix c.");
     let config = Config {
-        application_commands: vec![COMPLETE_NEXT_COMMAND, COMPLETE_PREVIOUS_COMMAND, COPY, CUT, ENTRY_DELETE_NEXT_CHAR,
-            ENTRY_DELETE_NEXT_WORD, ENTRY_DELETE_PREVIOUS_WORD, ENTRY_END, ENTRY_NEXT_CHAR, ENTRY_NEXT_WORD,
-            ENTRY_PREVIOUS_CHAR, ENTRY_PREVIOUS_WORD, ENTRY_SMART_HOME, PASTE, PASTE_SELECTION],
+        application_commands: APPLICATION_COMMANDS.to_vec(),
         mapping_modes: modes.keys().cloned().collect(),
     };
     let mut parser = Parser::new_with_config(config);
     if let Some(include_path) = include_path {
         parser.set_include_path(include_path);
     }
+    (parser, modes)
+}
+
+/// Parse a configuration file.
+pub fn parse_config<P: AsRef<Path>, COMM: EnumFromStr>(filename: P, user_modes: Modes, include_path: Option<PathBuf>)
+    -> (Parser<COMM>, ParseResult<COMM>, ModesHash)
+{
+    let mut parse_result = ParseResult::new();
+    let (mut parser, modes) = create_parser(user_modes, include_path);
 
     let file = file::open(&filename);
     let file = rtry_no_return!(parse_result, file, { return (parser, parse_result, modes); });
@@ -101,3 +116,13 @@ pub fn parse_config<P: AsRef<Path>, COMM: EnumFromStr>(filename: P, user_modes:
     let parse_result = parser.parse(buf_reader, None);
     (parser, parse_result, modes)
 }
+
+/// Parse a configuration from a reader, for instance a default config embedded in the
+/// application with `include_str!()`.
+pub fn parse_config_reader<R: BufRead, COMM: EnumFromStr>(reader: R, user_modes: Modes,
+    include_path: Option<PathBuf>) -> (Parser<COMM>, ParseResult<COMM>, ModesHash)
+{
+    let (mut parser, modes) = create_parser(user_modes, include_path);
+    let parse_result = parser.parse(reader, None);
+    (parser, parse_result, modes)
+}
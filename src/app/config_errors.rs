@@ -0,0 +1,181 @@
+/*
+ * Copyright (c) 2021 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Report of the errors found when loading the configs, listed by the errors command.
+
+use std::fmt::{self, Display, Formatter};
+
+use mg_settings::{EnumFromStr, EnumMetaData, SettingCompletion, SpecialCommand};
+use mg_settings::errors::Error;
+use mg_settings::settings;
+
+use app::{Mg, ERRORS_COMMAND};
+
+/// An error found when loading a config.
+pub struct ConfigError {
+    /// The error message, without its position.
+    pub message: String,
+    /// The line and the column of the error, if known.
+    pub position: Option<(usize, usize)>,
+    /// The config file, or the config layer when it is not a file.
+    pub source: String,
+}
+
+impl ConfigError {
+    /// Create a config error from the error found in `source`.
+    pub fn new(source: &str, error: &Error) -> Self {
+        let message = error.to_string();
+        // The parse errors only show their position in their message.
+        let position =
+            match *error {
+                Error::Parse(_) => message.rfind(" on line ")
+                    .and_then(|index| parse_position(&message[index + " on ".len()..])
+                        .map(|position| (index, position))),
+                _ => None,
+            };
+        match position {
+            Some((index, position)) => ConfigError {
+                message: message[..index].to_string(),
+                position: Some(position),
+                source: source.to_string(),
+            },
+            None => ConfigError {
+                message,
+                position: None,
+                source: source.to_string(),
+            },
+        }
+    }
+
+    /// Get the location of the error, in the format source:line:column.
+    pub fn location(&self) -> String {
+        match self.position {
+            Some((line, column)) => format!("{}:{}:{}", self.source, line, column),
+            None => self.source.clone(),
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "{}: {}", self.location(), self.message)
+    }
+}
+
+/// Parse a position in the format "line 1, column 2".
+fn parse_position(text: &str) -> Option<(usize, usize)> {
+    let text = text.strip_prefix("line ")?;
+    let mut parts = text.splitn(2, ", column ");
+    let line = parts.next()?.parse().ok()?;
+    let column = parts.next()?.parse().ok()?;
+    Some((line, column))
+}
+
+impl<COMM, SETT> Mg<COMM, SETT>
+where COMM: Clone + EnumFromStr + EnumMetaData + SpecialCommand + 'static,
+      SETT: Default + EnumMetaData + settings::Settings + SettingCompletion + 'static,
+{
+    /// Add an error found in the config being loaded.
+    pub fn add_config_error(&mut self, source: &str, error: &Error) {
+        self.model.config_errors.push(ConfigError::new(source, error));
+        self.model.config_errors_changed = true;
+    }
+
+    /// Add an error found at `position` in the config being loaded.
+    pub fn add_config_error_at(&mut self, source: &str, position: (usize, usize), message: &str) {
+        self.model.config_errors.push(ConfigError {
+            message: message.to_string(),
+            position: Some(position),
+            source: source.to_string(),
+        });
+        self.model.config_errors_changed = true;
+    }
+
+    /// Show the errors found since `first_error`: the error itself if there is only one, or a
+    /// summary.
+    /// Return false if there were errors.
+    pub fn report_config_errors(&mut self, first_error: usize) -> bool {
+        let count = self.model.config_errors.len() - first_error;
+        let message =
+            match count {
+                0 => return true,
+                1 => self.model.config_errors[first_error].to_string(),
+                _ => format!("{} config errors (see :errors)", count),
+            };
+        self.error(Error::Msg(message));
+        false
+    }
+
+    /// Show the full message of an error from the errors command, numbered from 1.
+    pub fn show_config_error(&mut self, number: &str) {
+        let error = number.parse::<usize>().ok()
+            .and_then(|number| number.checked_sub(1))
+            .and_then(|index| self.model.config_errors.get(index))
+            .map(ToString::to_string);
+        match error {
+            Some(error) => self.message(&error),
+            None => self.error(Error::Msg(format!("No config error numbered {}", number))),
+        }
+    }
+
+    /// List the config errors in the completion view.
+    pub fn show_config_errors(&mut self) {
+        if self.model.config_errors.is_empty() {
+            self.info("No config errors");
+            return;
+        }
+        self.input_command(ERRORS_COMMAND.to_string());
+        self.show_completion();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mg_settings::errors::Error;
+    use mg_settings::key::parse_keys;
+
+    use super::{ConfigError, parse_position};
+
+    #[test]
+    fn test_parse_error_position() {
+        // The position of a parse error is only found in its message.
+        let error = parse_keys("<C-", 3, 7).unwrap_err();
+        let message = error.to_string();
+        let index = message.rfind(" on line 3, column ").expect("position in the parse error message");
+        let config_error = ConfigError::new("config", &error);
+        assert_eq!(config_error.message, &message[..index]);
+        assert_eq!(config_error.position, parse_position(&message[index + " on ".len()..]));
+        assert_eq!(config_error.position.map(|(line, _)| line), Some(3));
+        assert!(config_error.location().starts_with("config:3:"));
+
+        let config_error = ConfigError::new("config", &Error::Msg("Cannot open the config file".to_string()));
+        assert_eq!(config_error.position, None);
+        assert_eq!(config_error.to_string(), "config: Cannot open the config file");
+    }
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(parse_position("line 1, column 2"), Some((1, 2)));
+        assert_eq!(parse_position("line 12, column 34"), Some((12, 34)));
+        assert_eq!(parse_position("line 1"), None);
+        assert_eq!(parse_position("column 2, line 1"), None);
+    }
+}
@@ -0,0 +1,234 @@
+/*
+ * Copyright (c) 2021 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+use std::collections::HashMap;
+
+use gtk::{
+    traits::{GtkWindowExt, WidgetExt},
+    Widget,
+};
+use mg_settings::{self, EnumFromStr, EnumMetaData, SettingCompletion, SpecialCommand};
+use mg_settings::errors::{Error, Result};
+use mg_settings::key::{Key, parse_keys};
+
+use app::{Mappings, Mg};
+
+/// The option of the map commands giving the context of the mapping.
+pub const CONTEXT_OPTION: &str = "--context=";
+
+/// A context in which some mappings are available.
+pub enum Context {
+    /// The context is active when the predicate returns true.
+    Predicate(Box<dyn Fn() -> bool>),
+    /// The context is active when the focused widget, or one of its ancestors, has this name.
+    WidgetName(String),
+}
+
+/// The mappings scoped to a context, by context name.
+pub type ContextMappings = HashMap<String, Mappings>;
+
+/// The contexts registered by the application, by name, the predicate contexts being checked in
+/// this order.
+/// Since the config parser reads the context option of a map command as keys, the names can only
+/// contain the characters accepted in keys: ASCII letters and symbols like - and _, but no digits.
+pub type Contexts = Vec<(&'static str, Context)>;
+
+/// Extract the context of a mapping of the form `map --context=name keys action`.
+/// Since the config parser sees the context option as the keys, the real keys are the first word
+/// of the action.
+pub fn parse_context_mapping(keys: Vec<Key>, action: String) -> Result<(Option<String>, Vec<Key>, String)> {
+    let keys_string: String = keys.iter().map(ToString::to_string).collect();
+    if !keys_string.starts_with(CONTEXT_OPTION) {
+        return Ok((None, keys, action));
+    }
+    let context = keys_string[CONTEXT_OPTION.len()..].to_string();
+    if context.is_empty() {
+        return Err(Error::Msg("Missing context name in mapping".to_string()));
+    }
+    let action = action.trim();
+    let (keys, action) =
+        match action.find(char::is_whitespace) {
+            Some(index) => (&action[..index], action[index..].trim()),
+            None => return Err(Error::Msg(format!("Missing mapping action in context {}", context))),
+        };
+    // The position of the keys in the config is not known here, so it is not in the error.
+    let keys = parse_keys(keys, 1, 1)
+        .map_err(|error| match error {
+            Error::Parse(error) => Error::Msg(format!("Invalid keys in context {}: unexpected {}, expecting {}",
+                context, error.unexpected, error.expected)),
+            error => error,
+        })?;
+    Ok((Some(context), keys, action.to_string()))
+}
+
+/// Check that the context name can be used in the context option of a map command.
+fn is_valid_context_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains('<') && parse_keys(name, 1, 1).is_ok()
+}
+
+impl<COMM, SETT> Mg<COMM, SETT>
+    where COMM: Clone + EnumFromStr + EnumMetaData + SpecialCommand + 'static,
+          SETT: Default + EnumMetaData + mg_settings::settings::Settings + SettingCompletion + 'static,
+{
+    /// Get the names of the active contexts, the most specific first.
+    /// The widget contexts are sorted from the one closest to the focused widget and come before
+    /// the predicate contexts, which are in the order they were registered.
+    pub fn active_contexts(&self) -> Vec<&str> {
+        let focused_widget = self.widgets.window.focused_widget();
+        let mut widget_contexts = vec![];
+        let mut predicate_contexts = vec![];
+        for (name, context) in &self.model.contexts {
+            match context {
+                Context::Predicate(predicate) => {
+                    if predicate() {
+                        predicate_contexts.push(name.as_str());
+                    }
+                },
+                Context::WidgetName(widget_name) => {
+                    if let Some(distance) = focus_distance(focused_widget.clone(), widget_name) {
+                        widget_contexts.push((distance, name.as_str()));
+                    }
+                },
+            }
+        }
+        widget_contexts.sort_by_key(|&(distance, _)| distance);
+        widget_contexts.into_iter()
+            .map(|(_, name)| name)
+            .chain(predicate_contexts)
+            .collect()
+    }
+
+    /// Add a mapping, in the specified context if any.
+    pub fn add_mapping(&mut self, mode_prefix: &str, context: Option<String>, keys: Vec<Key>, action: String) {
+        let mode = self.model.modes[mode_prefix].name;
+        let origin = self.model.current_origin.clone();
+        self.model.mapping_origins.insert((mode, context.clone(), keys.clone()), origin);
+        let mappings =
+            match context {
+                Some(context) => self.model.context_mappings.entry(context).or_insert_with(HashMap::new),
+                None => &mut self.model.mappings,
+            };
+        mappings.entry(mode)
+            .or_insert_with(HashMap::new)
+            .insert(keys, action);
+        self.model.mappings_changed = true;
+    }
+
+    /// Get the action mapped to the keys in the specified mode.
+    /// The mapping of the most specific active context is used.
+    pub fn find_mapping(&self, mode: &str, keys: &[Key]) -> Option<String> {
+        self.mode_mappings(mode).into_iter()
+            .filter_map(|mappings| mappings.get(keys))
+            .next()
+            .cloned()
+    }
+
+    /// Get the mappings available in the specified mode, the most specific first.
+    pub fn mode_mappings(&self, mode: &str) -> Vec<&HashMap<Vec<Key>, String>> {
+        self.active_contexts().into_iter()
+            .filter_map(|context| self.model.context_mappings.get(context))
+            .chain(Some(&self.model.mappings))
+            .filter_map(|mappings| mappings.get(mode))
+            .collect()
+    }
+
+    /// Remove a mapping from the global mappings and from every context.
+    pub fn remove_mapping(&mut self, mode_prefix: &str, keys: &[Key]) {
+        let mode = self.model.modes[mode_prefix].name;
+        let contexts = self.model.context_mappings.values_mut();
+        for mappings in Some(&mut self.model.mappings).into_iter().chain(contexts) {
+            if let Some(mode_mappings) = mappings.get_mut(mode) {
+                mode_mappings.remove(keys);
+            }
+        }
+        self.model.mapping_origins
+            .retain(|(origin_mode, _, origin_keys), _| *origin_mode != mode || origin_keys != keys);
+        self.model.mappings_changed = true;
+    }
+
+    /// Set the contexts in which mappings can be scoped.
+    pub fn set_contexts(&mut self, contexts: Contexts) {
+        for &(name, _) in &contexts {
+            assert!(is_valid_context_name(name),
+                "Context name {} contains characters that cannot be used in a map command.", name);
+        }
+        self.model.contexts = contexts.into_iter()
+            .map(|(name, context)| (name.to_string(), context))
+            .collect();
+    }
+}
+
+/// Get the number of parents between the focused widget and its ancestor named `name`.
+fn focus_distance(focused_widget: Option<Widget>, name: &str) -> Option<usize> {
+    let mut widget = focused_widget;
+    let mut distance = 0;
+    while let Some(current) = widget {
+        if current.widget_name() == name {
+            return Some(distance);
+        }
+        widget = current.parent();
+        distance += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use mg_settings::key::{Key, parse_keys};
+    use mg_settings::key::Key::{Char, Control};
+
+    use super::{is_valid_context_name, parse_context_mapping};
+
+    /// Parse the keys and the action of `map keys action` like the config parser does.
+    fn parse_mapping(keys: &str, action: &str) -> Result<(Option<String>, Vec<Key>, String), String> {
+        let keys = parse_keys(keys, 1, 5).expect("keys");
+        parse_context_mapping(keys, action.to_string())
+            .map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn test_parse_context_mapping() {
+        assert_eq!(parse_mapping("--context=editor", "<C-a>b open foo"),
+            Ok((Some("editor".to_string()), vec![Control(Box::new(Char('a'))), Char('b')], "open foo".to_string())));
+        assert_eq!(parse_mapping("--context=editor", "  a   open  "),
+            Ok((Some("editor".to_string()), vec![Char('a')], "open".to_string())));
+        assert_eq!(parse_mapping("<C-a>b", "open foo"),
+            Ok((None, vec![Control(Box::new(Char('a'))), Char('b')], "open foo".to_string())));
+
+        assert_eq!(parse_mapping("--context=", "a open"), Err("Missing context name in mapping".to_string()));
+        assert_eq!(parse_mapping("--context=editor", "a"),
+            Err("Missing mapping action in context editor".to_string()));
+        assert_eq!(parse_mapping("--context=editor", "<C-a open"),
+            Err("Invalid keys in context editor: unexpected (none), expecting >".to_string()));
+        assert_eq!(parse_mapping("--context=editor", "<Foo> open"),
+            Err("Invalid keys in context editor: unexpected Foo, expecting special key".to_string()));
+    }
+
+    #[test]
+    fn test_is_valid_context_name() {
+        assert!(is_valid_context_name("editor"));
+        assert!(is_valid_context_name("text-view_"));
+        assert!(!is_valid_context_name(""));
+        assert!(!is_valid_context_name("editor2"));
+        assert!(!is_valid_context_name("text view"));
+        assert!(!is_valid_context_name("<Tab>"));
+    }
+}
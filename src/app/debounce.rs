@@ -0,0 +1,90 @@
+/*
+ * Copyright (c) 2021 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Coalescing of the updates done while the user types in the command entry.
+
+use std::mem;
+
+use mg_settings::{EnumFromStr, EnumMetaData, SettingCompletion, SpecialCommand};
+use mg_settings::settings;
+use relm::timeout;
+
+use app::{Mg, Mode};
+use app::ActivationType::Current;
+use app::Msg::InputQuiet;
+
+impl<COMM, SETT> Mg<COMM, SETT>
+where COMM: Clone + EnumFromStr + EnumMetaData + SpecialCommand + 'static,
+      SETT: Default + EnumMetaData + settings::Settings + SettingCompletion + 'static,
+{
+    /// Drop the pending updates, since the command is activated.
+    pub fn cancel_pending_updates(&mut self) {
+        self.model.pending_completion_update = false;
+        self.model.pending_incremental_command = false;
+    }
+
+    /// Process the pending updates now, for instance before selecting a completion.
+    pub fn flush_pending_updates(&mut self) {
+        if mem::replace(&mut self.model.pending_completion_update, false) {
+            self.update_completions();
+        }
+        if mem::replace(&mut self.model.pending_incremental_command, false) &&
+            self.model.current_mode.get() == Mode::Command
+        {
+            let command = self.model.status_bar_command.clone();
+            if let Some(msg) = self.handle_special_command(Current, &command) {
+                self.model.relm.stream().emit(msg);
+            }
+        }
+    }
+
+    /// Process the pending updates if nothing was typed since they were scheduled.
+    pub fn input_quiet(&mut self, generation: usize) {
+        if generation == self.model.update_generation {
+            self.flush_pending_updates();
+        }
+    }
+
+    /// Update the completions once the user stops typing.
+    pub fn schedule_completion_update(&mut self) {
+        self.model.pending_completion_update = true;
+        self.schedule_pending_updates();
+    }
+
+    /// Execute the incremental command once the user stops typing.
+    pub fn schedule_incremental_command(&mut self) {
+        self.model.pending_incremental_command = true;
+        self.schedule_pending_updates();
+    }
+
+    /// Process the pending updates after the update delay, so that only the latest input is
+    /// processed when typing fast.
+    fn schedule_pending_updates(&mut self) {
+        self.model.update_generation = self.model.update_generation.wrapping_add(1);
+        if self.model.update_delay == 0 {
+            self.flush_pending_updates();
+        }
+        else {
+            let generation = self.model.update_generation;
+            timeout(self.model.relm.stream(), self.model.update_delay, move || InputQuiet(generation));
+        }
+    }
+}
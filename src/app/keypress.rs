@@ -34,7 +34,6 @@ use mg_settings::{
 };
 
 use app::{Mg, Mode};
-use app::ActivationType::Current;
 use app::Msg::{self, EnterNormalModeAndReset};
 
 impl<COMM, SETT> Mg<COMM, SETT>
@@ -53,9 +52,7 @@ impl<COMM, SETT> Mg<COMM, SETT>
     /// Handle the key release event for the command mode.
     fn command_key_release(&mut self, _key: &EventKey) -> Option<Msg<COMM, SETT>> {
         if !self.is_normal_command() && COMM::is_incremental(self.model.current_command_mode) {
-            let command = self.model.status_bar_command.clone(); // TODO: remove this useless clone.
-            let msg = self.handle_special_command(Current, &command);
-            return msg;
+            self.schedule_incremental_command();
         }
         None
     }
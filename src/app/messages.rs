@@ -0,0 +1,139 @@
+/*
+ * Copyright (c) 2021 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! History of the messages shown to the user, listed by the messages command.
+
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use glib::DateTime;
+use mg_settings::{EnumFromStr, EnumMetaData, SettingCompletion, SpecialCommand};
+use mg_settings::errors::Error;
+use mg_settings::settings;
+
+use app::{Mg, MESSAGES_COMMAND};
+use app::Msg::MessageLogged;
+
+/// The number of messages kept in the history.
+const MESSAGE_HISTORY_SIZE: usize = 100;
+
+/// The level of a message shown to the user.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MessageLevel {
+    /// An alert, shown in blue until the next message.
+    Alert,
+    /// An error, shown in red until the next message.
+    Error,
+    /// An information message, shown for 5 seconds.
+    Info,
+    /// A message without color, shown until the next message.
+    Message,
+    /// A warning, shown in orange for 5 seconds.
+    Warning,
+}
+
+impl Display for MessageLevel {
+    fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
+        let level =
+            match *self {
+                MessageLevel::Alert => "alert",
+                MessageLevel::Error => "error",
+                MessageLevel::Info => "info",
+                MessageLevel::Message => "message",
+                MessageLevel::Warning => "warning",
+            };
+        write!(formatter, "{}", level)
+    }
+}
+
+/// A message shown to the user, kept in the history.
+#[derive(Clone, Debug)]
+pub struct LoggedMessage {
+    /// The level of the message, which decides how it was shown.
+    pub level: MessageLevel,
+    /// The text of the message, as shown in the status bar.
+    pub text: String,
+    /// The time at which the message was shown.
+    pub time: SystemTime,
+}
+
+impl LoggedMessage {
+    /// Get the time of the message in the local time zone, in the format HH:MM:SS.
+    pub fn time_string(&self) -> String {
+        let seconds = self.time.duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as i64)
+            .unwrap_or_default();
+        DateTime::from_unix_local(seconds).ok()
+            .and_then(|time| time.format("%H:%M:%S").ok())
+            .map(|time| time.to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Add a message to the history, dropping the oldest one if the history is full.
+fn push_message(history: &mut VecDeque<LoggedMessage>, message: LoggedMessage) {
+    if history.len() == MESSAGE_HISTORY_SIZE {
+        history.pop_front();
+    }
+    history.push_back(message);
+}
+
+impl<COMM, SETT> Mg<COMM, SETT>
+where COMM: Clone + EnumFromStr + EnumMetaData + SpecialCommand + 'static,
+      SETT: Default + EnumMetaData + settings::Settings + SettingCompletion + 'static,
+{
+    /// Add a message shown to the user to the history.
+    pub fn log_message(&mut self, level: MessageLevel, text: &str) {
+        let message = LoggedMessage {
+            level,
+            text: text.to_string(),
+            time: SystemTime::now(),
+        };
+        push_message(&mut self.model.message_history, message.clone());
+        self.model.messages_changed = true;
+        self.model.relm.stream().emit(MessageLogged(message));
+    }
+
+    /// Send the message history, the oldest message first, to the callback.
+    pub fn send_message_history(&self, callback: Box<dyn Fn(Vec<LoggedMessage>)>) {
+        callback(self.model.message_history.iter().cloned().collect());
+    }
+
+    /// Show again a message from the messages command, numbered from 1 for the most recent.
+    pub fn show_logged_message(&mut self, number: &str) {
+        let text = number.parse::<usize>().ok()
+            .and_then(|number| number.checked_sub(1))
+            .and_then(|index| self.model.message_history.iter().rev().nth(index))
+            .map(|message| message.text.clone());
+        match text {
+            // Not logged again.
+            Some(text) => self.show_message(&text),
+            None => self.error(Error::Msg(format!("No message numbered {}", number))),
+        }
+    }
+
+    /// List the messages in the completion view.
+    pub fn show_messages(&mut self) {
+        self.input_command(MESSAGES_COMMAND.to_string());
+        self.show_completion();
+    }
+}
@@ -23,15 +23,23 @@ mod app_completion;
 mod color;
 mod command;
 mod config;
+mod config_errors;
+pub mod context;
+mod debounce;
 pub mod dialog;
 mod keypress;
+mod messages;
+mod set_command;
 pub mod settings;
 mod shortcut;
+mod source;
 pub mod status_bar;
+mod suggestion;
+mod write_config;
 
 use std::cell::Cell;
 use std::char;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::io;
 use std::path::PathBuf;
 use std::rc::Rc;
@@ -52,6 +60,7 @@ use gtk::{
     PackType,
 };
 use gtk::Orientation::Vertical;
+use gtk::gio;
 use mg_settings::{
     self,
     EnumFromStr,
@@ -59,8 +68,8 @@ use mg_settings::{
     Parser,
     SettingCompletion,
     SpecialCommand,
+    Value,
 };
-use mg_settings::ParseResult;
 use mg_settings::errors;
 use mg_settings::key::Key;
 use relm::{
@@ -72,8 +81,16 @@ use relm::{
 };
 use relm_derive::widget;
 
+use app::app_completion::{MappingCommands, mapping_commands};
+use app::source::RUNTIME_ORIGIN;
+use app::config_errors::ConfigError;
+pub use app::messages::{LoggedMessage, MessageLevel};
+use app::write_config::MappingOverride;
 use app::config::create_default_config;
-pub use app::config::parse_config;
+use app::config::create_parser;
+pub use app::config::{parse_config, parse_config_reader};
+pub use app::source::ConfigLayer;
+use app::context::{Context, ContextMappings, Contexts};
 use app::dialog::Responder;
 use app::settings::DefaultConfig;
 use app::shortcut::shortcut_to_string;
@@ -84,9 +101,16 @@ use completion::{
     NO_COMPLETER_IDENT,
 };
 use completion::completion_view::Msg::{
+    Action,
+    ActionRows,
+    Activate,
+    AddArgumentCompleters,
     AddCompleters,
     Completer,
     CompletionChange,
+    FirstCompletion,
+    PrefixCompletion,
+    SetConfig,
     Visible,
 };
 use self::color::{color_blue, color_orange, color_red};
@@ -96,9 +120,11 @@ use self::status_bar::Msg::{
     BarVisible,
     EntryActivate,
     EntryChanged,
+    EntryCursorMoved,
     EntryShown,
     EntryText,
     Identifier,
+    Suggestion,
 };
 use self::status_bar::ItemMsg::Text;
 use self::Msg::*;
@@ -132,9 +158,15 @@ pub enum ShortcutCommand {
 const BLOCKING_INPUT_MODE: &str = "blocking-input";
 pub const COMMAND_MODE: &str = "command";
 const COMPLETE_NEXT_COMMAND: &str = "complete-next";
+const COMPLETE_PREFIX_COMMAND: &str = "complete-prefix";
 const COMPLETE_PREVIOUS_COMMAND: &str = "complete-previous";
+const COMPLETION_INVERT_MARKS: &str = "completion-invert-marks";
+const COMPLETION_MARK_ALL: &str = "completion-mark-all";
+const COMPLETION_TOGGLE_MARK: &str = "completion-toggle-mark";
 const COPY: &str = "entry-copy";
 const CUT: &str = "entry-cut";
+const DEFAULT_UPDATE_DELAY: u32 = 0;
+const ENTRY_ACCEPT_SUGGESTION: &str = "entry-accept-suggestion";
 const ENTRY_DELETE_NEXT_CHAR: &str = "entry-delete-next-char";
 const ENTRY_DELETE_NEXT_WORD: &str = "entry-delete-next-word";
 const ENTRY_DELETE_PREVIOUS_WORD: &str = "entry-delete-previous-word";
@@ -144,12 +176,17 @@ const ENTRY_NEXT_WORD: &str = "entry-next-word";
 const ENTRY_PREVIOUS_CHAR: &str = "entry-previous-char";
 const ENTRY_PREVIOUS_WORD: &str = "entry-previous-word";
 const ENTRY_SMART_HOME: &str = "entry-smart-home";
+const ERRORS_COMMAND: &str = "errors";
 const INFO_MESSAGE_DURATION: u32 = 5000;
 const INPUT_MODE: &str = "input";
+const MESSAGES_COMMAND: &str = "messages";
+const MKSETTINGS_COMMAND: &str = "mksettings";
 const NORMAL_MODE: &str = "normal";
 const PASTE: &str = "entry-paste";
 const PASTE_SELECTION: &str = "entry-paste-selection";
 const QUESTION_MODE: &str = "question";
+const SOURCE_COMMAND: &str = "source";
+const WRITE_CONFIG_COMMAND: &str = "write-config";
 
 #[derive(PartialEq)]
 pub enum ActivationType {
@@ -163,29 +200,90 @@ where COMM: Clone + EnumFromStr + EnumMetaData + SpecialCommand + 'static,
 {
     answer: String, // TODO: is this field even used?
     choices: Vec<char>,
+    command_history: Vec<String>,
+    completion_suggestion: Option<String>,
     completion_view: Component<CompletionView>,
+    /// The errors found when loading the configs.
+    config_errors: Vec<ConfigError>,
+    config_errors_changed: bool,
+    /// The configs to load, in order.
+    config_layers: Vec<ConfigLayer>,
+    /// Whether the config layers were loaded a first time.
+    config_loaded: bool,
+    /// The monitors of the config files, to reload the config when they change.
+    config_monitors: Vec<gio::FileMonitor>,
+    /// The config file or layer being executed, whose errors are added to the config errors
+    /// instead of being shown.
+    config_source: Option<String>,
+    /// The path of the user's config file.
+    config_path: Option<PathBuf>,
+    context_mappings: ContextMappings,
+    contexts: Vec<(String, Context)>,
     current_command_mode: char,
     current_mode: Rc<Cell<Mode>>,
+    /// The origin of the commands being executed: a config layer or the runtime.
+    current_origin: String,
     current_shortcut: Vec<Key>,
     entry_shown: bool,
     foreground_color: RGBA,
+    include_path: Option<PathBuf>,
     initial_errors: Vec<errors::Error>,
-    initial_parse_result: Option<ParseResult<COMM>>,
     input_callback: Option<Box<dyn Fn(Option<String>, bool)>>,
+    mapping_commands: Vec<MappingCommands>,
+    /// The config layer or file each mapping comes from, by mode name, context and keys.
+    mapping_origins: HashMap<(&'static str, Option<String>, Vec<Key>), String>,
+    /// The mappings changed at runtime, to write to the config.
+    mapping_overrides: Vec<MappingOverride>,
     mappings: Mappings,
+    /// Whether the key bindings changed since the completers of the unmap commands were updated.
+    mappings_changed: bool,
     message: String,
+    /// The last messages shown to the user, the oldest first.
+    message_history: VecDeque<LoggedMessage>,
+    messages_changed: bool,
     mode_label: String,
     mode_string: String,
     modes: ModesHash,
+    pending_completion_update: bool,
+    pending_incremental_command: bool,
+    /// Whether the changes to settings and mappings are recorded to be written to the config,
+    /// which is the case once the config is loaded.
+    record_overrides: bool,
     relm: Relm<Mg<COMM, SETT>>,
+    reload_pending: bool,
+    /// The default values of the settings registered by the application, as text.
+    setting_defaults: HashMap<String, String>,
+    /// The config layer or file each setting value comes from.
+    setting_origins: HashMap<String, String>,
+    /// The settings changed at runtime, to write to the config, with None as the value of the
+    /// settings changed with SetSetting.
+    setting_overrides: BTreeMap<String, Option<String>>,
+    /// The values of the settings changed by the set commands, as text.
+    setting_values: HashMap<String, String>,
     settings: SETT,
+    /// Whether the setting values changed since the setting completer was updated.
+    settings_changed: bool,
     settings_parser: Box<Parser<COMM>>,
     shortcuts: HashMap<Key, String>,
     shortcut_pressed: bool,
     show_count: bool,
+    /// The config files sourced at runtime, loaded again after the config layers on reload.
+    sourced_layers: Vec<ConfigLayer>,
     status_bar_command: String,
+    /// The cursor position in the command entry, None meaning at the end.
+    status_bar_cursor: Option<usize>,
+    /// The end of the suggested command, shown after the text of the command entry.
+    status_bar_suggestion: String,
     status_bar_visible: bool,
+    /// The delay, in milliseconds, without typing before updating the completions and
+    /// executing the incremental commands.
+    update_delay: u32,
+    update_generation: usize,
+    /// The settings changed by the application with SetSetting, whose values are unknown since the
+    /// settings cannot be read.
+    unknown_setting_values: HashSet<String>,
     variables: HashMap<String, Box<dyn Fn() -> String>>,
+    watch_config: bool,
 }
 
 #[allow(missing_docs)]
@@ -196,13 +294,23 @@ pub enum Msg<COMM, SETT>
 {
     Alert(String),
     AppClose,
+    ArgumentCompleters(completion::ArgumentCompleters),
     BlockingCustomDialog(Box<dyn Responder>, DialogBuilder),
     BlockingInput(Box<dyn Responder>, String, String),
     BlockingQuestion(Box<dyn Responder>, String, Vec<char>),
     BlockingYesNoQuestion(Box<dyn Responder>, String),
     CloseWin,
-    Completers(HashMap<&'static str, Box<dyn completion::Completer>>),
+    Completers(completion::Completers),
+    CompletionAction(String),
+    CompletionActionItems(String, Vec<Vec<String>>),
+    CompletionViewActivate,
     CompletionViewChange(String),
+    CompletionViewConfig(completion::ViewConfig),
+    CompletionViewPrefix(String),
+    CompletionViewSuggestion(Option<String>),
+    ConfigFileChanged,
+    ConfigLayers(Vec<ConfigLayer>),
+    Contexts(Contexts),
     CustomCommand(COMM),
     CustomDialog(DialogBuilder),
     DarkTheme(bool),
@@ -216,21 +324,34 @@ pub enum Msg<COMM, SETT>
     Info(String),
     InitAfter,
     Input(Box<dyn Responder>, String, String),
+    InputQuiet(usize),
     KeyPress(EventKey),
     KeyRelease(EventKey),
+    LoadConfig,
     Message(String),
+    MessageHistory(Box<dyn Fn(Vec<LoggedMessage>)>),
+    MessageLogged(LoggedMessage),
     ModeChanged(String),
     Question(Box<dyn Responder>, String, &'static [char]),
+    ReloadConfig,
     ResetInput,
     SetMode(&'static str),
     SetSetting(SETT::Variant),
+    SetSettingValue(String, Value),
     SettingChanged(SETT::Variant),
+    SettingDefaults(Vec<(&'static str, Value)>),
+    ShowConfigErrors,
+    ShowMessages,
     StatusBarEntryActivate(String),
     StatusBarEntryChanged(String),
+    StatusBarEntryCursorMoved(i32),
     StatusBarVisible(bool),
     Title(String),
+    UpdateDelay(u32),
     Variables(Variables),
+    WatchConfig(bool),
     Warning(String),
+    WriteConfig(Option<PathBuf>),
     YesNoQuestion(Box<dyn Responder>, String),
 }
 
@@ -244,26 +365,29 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
     fn after_children_added(&mut self) {
         // NOTE: This code is not in init_view() because the SettingChanged signal would be sent
         // before the user's code connected to this event.
-        let parse_result = self.model.initial_parse_result.take().expect("initial parse result");
-        self.execute_commands(parse_result, false);
-        let errors: Vec<_> = self.model.initial_errors.drain(..).collect();
-        for error in errors {
-            self.error(error);
-        }
+        // The config is loaded after the messages sent by the user when creating the widget, like
+        // ConfigLayers, so that it is only loaded once.
+        self.model.relm.stream().emit(LoadConfig);
     }
 
     /// Show an alert message to the user.
     fn alert(&mut self, message: &str) {
+        self.log_message(MessageLevel::Alert, message);
         self.model.message = message.to_string();
         color_blue(&self.widgets.status_bar);
     }
 
     /// Show an error to the user.
     fn error(&mut self, error: errors::Error) {
+        if let Some(source) = self.model.config_source.clone() {
+            self.add_config_error(&source, &error);
+            return;
+        }
         let mut message = String::new();
         let error_str = error.to_string();
         message.push_str(&error_str);
         error!("{}", message);
+        self.log_message(MessageLevel::Error, &error_str);
 
         self.model.message = error_str;
         self.model.entry_shown = false;
@@ -288,6 +412,7 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
     /// Show an information message to the user for 5 seconds.
     fn info(&mut self, message: &str) {
         info!("{}", message);
+        self.log_message(MessageLevel::Info, message);
         let message = message.to_string();
         self.model.message = message.clone();
         self.reset_colors();
@@ -297,6 +422,12 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
 
     /// Show a message to the user.
     fn message(&mut self, message: &str) {
+        self.log_message(MessageLevel::Message, message);
+        self.show_message(message);
+    }
+
+    /// Show a message to the user without adding it to the message history.
+    fn show_message(&mut self, message: &str) {
         self.reset_colors();
         self.model.message = message.to_string();
     }
@@ -304,6 +435,7 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
     /// Show a warning message to the user for 5 seconds.
     fn warning(&mut self, message: &str) {
         warn!("{}", message);
+        self.log_message(MessageLevel::Warning, message);
         let message = message.to_string();
         self.model.message = message.clone();
         color_orange(&self.widgets.status_bar);
@@ -315,6 +447,7 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
     fn hide_entry_and_completion(&mut self) {
         self.model.completion_view.stream().emit(Visible(false));
         self.model.entry_shown = false;
+        self.clear_suggestion();
     }
 
     fn init_view(&mut self) {
@@ -324,9 +457,17 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
         let completion_widget = self.model.completion_view.widget();
         self.set_completer(DEFAULT_COMPLETER_IDENT);
         self.model.completion_view.stream().emit(Visible(false));
+        self.model.completion_view.emit(AddArgumentCompleters(self.default_argument_completers()));
         let completion_view = &self.model.completion_view;
         connect!(completion_view@CompletionChange(ref completion), self.model.relm,
             CompletionViewChange(completion.clone()));
+        connect!(completion_view@ActionRows(ref action, ref rows), self.model.relm,
+            CompletionActionItems(action.clone(), rows.clone()));
+        connect!(completion_view@Activate, self.model.relm, CompletionViewActivate);
+        connect!(completion_view@PrefixCompletion(ref completion), self.model.relm,
+            CompletionViewPrefix(completion.clone()));
+        connect!(completion_view@FirstCompletion(ref completion), self.model.relm,
+            CompletionViewSuggestion(completion.clone()));
         completion_widget.set_hexpand(true);
         completion_widget.set_vexpand(true);
         self.widgets.overlay.add_overlay(completion_widget);
@@ -353,43 +494,75 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
         if let Err(error) = create_default_config(default_config) {
             initial_errors.push(error.into());
         }
-        let (settings_parser, initial_parse_result, modes) =
-            match settings_filename {
-                Ok(settings_filename) => {
-                    let (parser, parse_result, modes) = parse_config(settings_filename, user_modes, include_path);
-                    (Box::new(parser), Some(parse_result), modes)
-                },
-                Err(error) => {
-                    initial_errors.push(error.into());
-                    (Box::new(Parser::<COMM>::new()), None, HashMap::new())
-                },
-            };
+        let config_path = settings_filename.as_ref().ok().cloned();
+        let model_include_path = include_path.clone();
+        // The config file is loaded with the config layers, by load_initial_config().
+        if let Err(error) = settings_filename {
+            // The config layers can still be loaded.
+            initial_errors.push(error.into());
+        }
+        let (settings_parser, modes) = create_parser(user_modes, include_path);
+        let settings_parser = Box::new(settings_parser);
         Model {
             answer: String::new(),
             choices: vec![],
-            completion_view: create_component::<CompletionView>(Self::default_completers()),
+            command_history: vec![],
+            completion_suggestion: None,
+            completion_view: create_component::<CompletionView>(Self::default_mode_completers(&modes)),
+            config_errors: vec![],
+            config_errors_changed: true,
+            config_layers: vec![ConfigLayer::user()],
+            config_loaded: false,
+            config_monitors: vec![],
+            config_path,
+            config_source: None,
+            context_mappings: HashMap::new(),
+            contexts: vec![],
             current_command_mode: ':',
             current_mode: Rc::new(Cell::new(Mode::Normal)),
+            current_origin: RUNTIME_ORIGIN.to_string(),
             current_shortcut: vec![],
             entry_shown: false,
             foreground_color: RGBA::WHITE,
+            include_path: model_include_path,
             initial_errors,
-            initial_parse_result,
             input_callback: None,
+            mapping_commands: mapping_commands(&modes),
+            mapping_origins: HashMap::new(),
+            mapping_overrides: vec![],
             mappings: HashMap::new(),
+            mappings_changed: true,
             message: String::new(),
+            message_history: VecDeque::new(),
+            messages_changed: true,
             mode_label: String::new(),
             mode_string: NORMAL_MODE.to_string(),
             modes,
+            pending_completion_update: false,
+            pending_incremental_command: false,
+            record_overrides: false,
             relm: relm.clone(),
+            reload_pending: false,
+            setting_defaults: HashMap::new(),
+            setting_origins: HashMap::new(),
+            setting_overrides: BTreeMap::new(),
+            setting_values: HashMap::new(),
             settings: SETT::default(),
+            settings_changed: true,
             settings_parser,
             shortcuts: HashMap::new(),
             shortcut_pressed: false,
             show_count: true,
+            sourced_layers: vec![],
             status_bar_command: String::new(),
+            status_bar_cursor: None,
+            status_bar_suggestion: String::new(),
             status_bar_visible: true,
+            update_delay: DEFAULT_UPDATE_DELAY,
+            update_generation: 0,
+            unknown_setting_values: HashSet::new(),
             variables: HashMap::new(),
+            watch_config: false,
         }
     }
 
@@ -448,6 +621,8 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
 
     fn set_input(&mut self, original_input: &str) {
         self.model.status_bar_command = original_input.to_string();
+        self.model.status_bar_cursor = None;
+        self.clear_suggestion();
     }
 
     /// Set the current mode.
@@ -469,6 +644,12 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
             self.model.mode_label = String::new();
         }
         self.model.current_mode.set(current_mode);
+        if current_mode == Mode::Command {
+            self.update_error_completer();
+            self.update_mapping_completers();
+            self.update_message_completer();
+            self.update_setting_completer();
+        }
         self.model.relm.stream().emit(ModeChanged(mode.to_string()));
 
         for mode in self.model.modes.values() {
@@ -478,8 +659,15 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
         }
     }
 
+    /// Set the suggestion shown after the text of the command entry.
+    /// The model is only bound to the view in this impl, so the suggestion must be set here.
+    fn set_status_bar_suggestion(&mut self, suggestion: String) {
+        self.model.status_bar_suggestion = suggestion;
+    }
+
     fn show_entry(&mut self) {
         self.model.entry_shown = true;
+        self.model.status_bar_cursor = None;
     }
 
     fn update(&mut self, event: Msg<COMM, SETT>) {
@@ -487,6 +675,8 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
             Alert(msg) => self.alert(&msg),
             // To be listened to by the user.
             AppClose => (),
+            ArgumentCompleters(argument_completers) =>
+                self.model.completion_view.emit(AddArgumentCompleters(argument_completers)),
             BlockingCustomDialog(responder, builder) =>
                 self.blocking_custom_dialog(responder, builder),
             BlockingInput(responder, question, default_answer) =>
@@ -495,7 +685,24 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
             BlockingYesNoQuestion(responder, question) => self.blocking_yes_no_question(responder, question),
             CloseWin => unsafe { self.widgets.window.destroy() },
             Completers(completers) => self.model.completion_view.emit(AddCompleters(completers)),
+            CompletionAction(action) => self.model.completion_view.emit(Action(action)),
+            // To be listened to by the user.
+            CompletionActionItems(_, _) => (),
+            CompletionViewActivate => {
+                let input = self.model.status_bar_command.clone();
+                self.command_activate(input);
+            },
             CompletionViewChange(completion) => self.set_input(&completion),
+            CompletionViewConfig(config) => self.model.completion_view.emit(SetConfig(config)),
+            CompletionViewPrefix(completion) => {
+                // The input was extended, so the completions must be filtered with it.
+                self.set_input(&completion);
+                self.update_completions();
+            },
+            CompletionViewSuggestion(completion) => self.set_completion_suggestion(completion),
+            ConfigFileChanged => self.config_file_changed(),
+            ConfigLayers(layers) => self.set_config_layers(layers),
+            Contexts(contexts) => self.set_contexts(contexts),
             // To be listened to by the user.
             CustomCommand(_) => (),
             CustomDialog(builder) => self.show_dialog(builder),
@@ -523,25 +730,41 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
             Info(msg) => self.info(&msg),
             InitAfter => self.after_children_added(),
             Input(responder, input, default_answer) => self.input(responder, input, default_answer),
+            InputQuiet(generation) => self.input_quiet(generation),
             Message(msg) => self.message(&msg),
+            MessageHistory(callback) => self.send_message_history(callback),
             KeyPress(key) => self.key_press(&key),
             KeyRelease(key) => self.key_release(&key),
+            LoadConfig => self.load_initial_config(),
             Error(error) => self.error(error),
             HideColoredMessage(message) => self.hide_colored_message(&message),
             HideInfo(message) => self.hide_info(&message),
             // To be listened by the user.
-            ModeChanged(_) | SettingChanged(_) => (),
+            MessageLogged(_) | ModeChanged(_) | SettingChanged(_) => (),
             Question(responder, question, choices) => self.question(responder, question, choices),
+            ReloadConfig => self.reload_config(),
             ResetInput => self.reset_input(),
             SetMode(mode) => self.set_mode(mode),
-            SetSetting(setting) => self.set_setting(setting),
+            SetSetting(setting) => self.set_setting_variant(setting),
+            SetSettingValue(name, value) => self.set_setting_value(name, value),
+            SettingDefaults(defaults) => self.set_setting_defaults(defaults),
+            ShowConfigErrors => self.show_config_errors(),
+            ShowMessages => self.show_messages(),
             StatusBarEntryActivate(input) => self.command_activate(input),
             StatusBarEntryChanged(input) => {
                 // NOTE: Lock to prevent moving the cursor of the command entry.
                 let _lock = self.streams.status_bar.stream().lock();
+                // The cursor is moved after the text is changed, so keep it at the end when it was
+                // there.
+                let previous_len = self.model.status_bar_command.chars().count();
+                if self.model.status_bar_cursor == Some(previous_len) {
+                    self.model.status_bar_cursor = None;
+                }
                 self.model.status_bar_command = input;
-                self.update_completions()
+                self.update_suggestion();
+                self.schedule_completion_update()
             },
+            StatusBarEntryCursorMoved(position) => self.cursor_moved(position as usize),
             StatusBarVisible(visible) => {
                 self.model.status_bar_visible = visible;
                 if !visible {
@@ -549,8 +772,11 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
                 }
             },
             Title(title) => self.set_title(&title),
+            UpdateDelay(delay) => self.model.update_delay = delay,
             Variables(variables) => self.set_variables(variables),
+            WatchConfig(watch) => self.watch_config(watch),
             Warning(message) => self.warning(&message),
+            WriteConfig(path) => self.write_config(path),
             YesNoQuestion(responder, question) => self.yes_no_question(responder, question),
         }
     }
@@ -573,6 +799,7 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
                     EntryShown: self.model.entry_shown,
                     EntryText: self.model.status_bar_command.clone(),
                     Identifier: self.model.current_command_mode.to_string(),
+                    Suggestion: self.model.status_bar_suggestion.clone(),
                     child: {
                         pack_type: PackType::End,
                     },
@@ -596,6 +823,7 @@ impl<COMM, SETT> Widget for Mg<COMM, SETT>
                     },
                     EntryActivate(ref input) => StatusBarEntryActivate(input.clone()),
                     EntryChanged(ref text) => StatusBarEntryChanged(text.clone()),
+                    EntryCursorMoved(position) => StatusBarEntryCursorMoved(position),
                 },
                 #[name="overlay"]
                 gtk::Overlay {
@@ -0,0 +1,309 @@
+/*
+ * Copyright (c) 2021 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! The forms of the set command added to the assignment: query, toggle, increment, decrement,
+//! multiply and reset.
+
+use mg_settings::{EnumFromStr, EnumMetaData, SettingCompletion, SpecialCommand, Value};
+use mg_settings::errors::{Error, SettingError};
+use mg_settings::settings;
+
+use app::Mg;
+use app::settings::{setting_type, value_to_string, variant_setting_name};
+use self::SetForm::{Add, Multiply, Query, Reset, Subtract, Toggle};
+
+/// A form of the set command which is not an assignment.
+#[derive(Debug, PartialEq)]
+pub enum SetForm {
+    /// `set name += value`: add to a number or append to a string.
+    Add(String),
+    /// `set name ^= value`: multiply a number or prepend to a string.
+    Multiply(String),
+    /// `set name?`: show the current value.
+    Query,
+    /// `set name&`: reset to the default value.
+    Reset,
+    /// `set name -= value`: subtract from a number.
+    Subtract(String),
+    /// `set name!`: toggle a boolean.
+    Toggle,
+}
+
+/// Parse the forms of the set command which are not handled by the config parser.
+/// Return None for the other commands, including the assignments.
+pub fn parse_set_form(command: &str) -> Option<(String, SetForm)> {
+    let command = command.trim();
+    let rest = command.strip_prefix("set")?;
+    if !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let rest = rest.trim();
+    let operator = ["+=", "-=", "^="].iter()
+        .filter_map(|&operator| rest.find(operator).map(|index| (index, operator)))
+        .min();
+    let (name, form) =
+        if let Some((index, operator)) = operator {
+            let operand = rest[index + operator.len()..].trim().to_string();
+            let form =
+                match operator {
+                    "+=" => Add(operand),
+                    "-=" => Subtract(operand),
+                    _ => Multiply(operand),
+                };
+            (&rest[..index], form)
+        }
+        else if let Some(name) = rest.strip_suffix('?') {
+            (name, Query)
+        }
+        else if let Some(name) = rest.strip_suffix('!') {
+            (name, Toggle)
+        }
+        else if let Some(name) = rest.strip_suffix('&') {
+            (name, Reset)
+        }
+        else {
+            return None;
+        };
+    let name = name.trim();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((name.to_string(), form))
+}
+
+impl<COMM, SETT> Mg<COMM, SETT>
+where COMM: Clone + EnumFromStr + EnumMetaData + SpecialCommand + 'static,
+      SETT: Default + EnumMetaData + settings::Settings + SettingCompletion + 'static,
+{
+    /// Get the current value of the setting, as text, if it is known.
+    fn current_setting_value(&self, name: &str) -> Option<&String> {
+        if self.model.unknown_setting_values.contains(name) {
+            return None;
+        }
+        self.model.setting_values.get(name)
+            .or_else(|| self.model.setting_defaults.get(name))
+    }
+
+    /// Execute a form of the set command which is not an assignment.
+    pub fn set_form(&mut self, name: String, form: SetForm) {
+        let typ =
+            match setting_type::<SETT>(&name) {
+                Some(typ) => typ,
+                None => {
+                    self.error(Error::Setting(SettingError::UnknownSetting(name)));
+                    return;
+                },
+            };
+        let value =
+            match form {
+                Query => {
+                    let origin = self.model.setting_origins.get(&name)
+                        .map(String::as_str)
+                        .unwrap_or("default");
+                    let message =
+                        match self.current_setting_value(&name) {
+                            Some(value) => format!("{} = {} (from {})", name, value, origin),
+                            None => format!("The value of {} is unknown", name),
+                        };
+                    self.message(&message);
+                    return;
+                },
+                Reset =>
+                    match self.model.setting_defaults.get(&name) {
+                        Some(value) => parse_value(&typ, value),
+                        None => Err(format!("No default value is registered for {}", name)),
+                    },
+                Toggle =>
+                    if typ == "bool" {
+                        self.current_setting_value(&name)
+                            .ok_or_else(|| format!("Cannot toggle {}: its current value is unknown", name))
+                            .and_then(|value| parse_value(&typ, value))
+                            .and_then(|value| toggle(&name, value))
+                    }
+                    else {
+                        Err(format!("Cannot toggle {}: expecting a bool setting, but it is a {}", name, typ))
+                    },
+                Add(ref operand) | Multiply(ref operand) | Subtract(ref operand) => {
+                    let operator =
+                        match form {
+                            Add(_) => "+=",
+                            Subtract(_) => "-=",
+                            _ => "^=",
+                        };
+                    self.current_setting_value(&name)
+                        .ok_or_else(|| format!("Cannot change {}: its current value is unknown", name))
+                        .and_then(|value| parse_value(&typ, value))
+                        .and_then(|current| apply_operation(&name, current, operator, operand))
+                },
+            };
+        match value {
+            Ok(value) => self.set_setting_value(name, value),
+            Err(message) => self.error(Error::Msg(message)),
+        }
+    }
+
+    /// Set the value of a setting and remember it to show it in the completion.
+    pub fn set_setting_value(&mut self, name: String, value: Value) {
+        let text = value_to_string(&value);
+        match SETT::to_variant(&name, value) {
+            Ok(setting) => {
+                self.set_setting(setting);
+                self.record_setting_override(&name, Some(&text));
+                self.model.setting_origins.insert(name.clone(), self.model.current_origin.clone());
+                self.model.unknown_setting_values.remove(&name);
+                self.model.setting_values.insert(name, text);
+                self.model.settings_changed = true;
+            },
+            Err(error) => self.error(Error::Msg(format!("Error setting {}: {}", name, error))),
+        }
+    }
+
+    /// Set a setting changed by the application.
+    /// Its value cannot be read from the variant, so it becomes unknown instead of keeping the
+    /// previous one.
+    pub fn set_setting_variant(&mut self, setting: SETT::Variant) {
+        match variant_setting_name::<SETT>(&setting) {
+            Some(name) => {
+                self.model.setting_values.remove(&name);
+                self.model.setting_origins.insert(name.clone(), self.model.current_origin.clone());
+                self.record_setting_override(&name, None);
+                self.model.unknown_setting_values.insert(name);
+            },
+            // Any setting might have changed.
+            None => {
+                self.model.setting_values.clear();
+                self.model.unknown_setting_values.extend(SETT::get_metadata().into_keys());
+            },
+        }
+        self.model.settings_changed = true;
+        self.set_setting(setting);
+    }
+}
+
+/// Apply the operator (+=, -= or ^=) with the operand to the current value.
+fn apply_operation(name: &str, current: Value, operator: &str, operand: &str) -> Result<Value, String> {
+    let expecting = |typ: &str| format!("Cannot change {} with {}: expecting {}", name, operand, typ);
+    match current {
+        Value::Int(value) => {
+            let operand: i64 = operand.parse().map_err(|_| expecting("an integer"))?;
+            let result =
+                match operator {
+                    "+=" => value.checked_add(operand),
+                    "-=" => value.checked_sub(operand),
+                    _ => value.checked_mul(operand),
+                };
+            result.map(Value::Int)
+                .ok_or_else(|| format!("Cannot change {}: the result is too large", name))
+        },
+        Value::Float(value) => {
+            let operand: f64 = operand.parse().map_err(|_| expecting("a number"))?;
+            let result =
+                match operator {
+                    "+=" => value + operand,
+                    "-=" => value - operand,
+                    _ => value * operand,
+                };
+            Ok(Value::Float(result))
+        },
+        Value::Str(value) =>
+            match operator {
+                "+=" => Ok(Value::Str(value + operand)),
+                "^=" => Ok(Value::Str(operand.to_string() + &value)),
+                _ => Err(format!("Cannot subtract from {}: -= only works on numbers", name)),
+            },
+        Value::Bool(_) =>
+            Err(format!("Cannot change {} with {}: it is a bool, use {}! instead", name, operator, name)),
+    }
+}
+
+/// Toggle the value of a bool setting.
+fn toggle(name: &str, value: Value) -> Result<Value, String> {
+    match value {
+        Value::Bool(value) => Ok(Value::Bool(!value)),
+        _ => Err(format!("Cannot toggle {}: expecting a bool setting", name)),
+    }
+}
+
+/// Parse the text of a value of a setting of this type.
+fn parse_value(typ: &str, text: &str) -> Result<Value, String> {
+    let error = || format!("Cannot read the value {} as a {}", text, typ);
+    match typ {
+        "bool" => text.parse().map(Value::Bool).map_err(|_| error()),
+        "float" => text.parse().map(Value::Float).map_err(|_| error()),
+        "integer" => text.parse().map(Value::Int).map_err(|_| error()),
+        _ => Ok(Value::Str(text.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mg_settings::Value;
+
+    use super::{apply_operation, parse_set_form, parse_value, toggle};
+    use super::SetForm::{Add, Multiply, Query, Reset, Subtract, Toggle};
+
+    #[test]
+    fn test_parse_set_form() {
+        assert_eq!(parse_set_form("set foo?"), Some(("foo".to_string(), Query)));
+        assert_eq!(parse_set_form("set foo!"), Some(("foo".to_string(), Toggle)));
+        assert_eq!(parse_set_form("set foo&"), Some(("foo".to_string(), Reset)));
+        assert_eq!(parse_set_form(" set  foo ? "), Some(("foo".to_string(), Query)));
+        assert_eq!(parse_set_form("set foo += 10"), Some(("foo".to_string(), Add("10".to_string()))));
+        assert_eq!(parse_set_form("set foo -= 10"), Some(("foo".to_string(), Subtract("10".to_string()))));
+        assert_eq!(parse_set_form("set foo ^= 10"), Some(("foo".to_string(), Multiply("10".to_string()))));
+        assert_eq!(parse_set_form("set foo+=a-=b"), Some(("foo".to_string(), Add("a-=b".to_string()))));
+        assert_eq!(parse_set_form("set foo = 10"), None);
+        assert_eq!(parse_set_form("set foo = a+=b"), None);
+        assert_eq!(parse_set_form("set foo bar?"), None);
+        assert_eq!(parse_set_form("set ?"), None);
+        assert_eq!(parse_set_form("setfoo?"), None);
+        assert_eq!(parse_set_form("open foo?"), None);
+    }
+
+    #[test]
+    fn test_apply_operation() {
+        assert_eq!(apply_operation("foo", Value::Int(5), "+=", "10"), Ok(Value::Int(15)));
+        assert_eq!(apply_operation("foo", Value::Int(5), "-=", "10"), Ok(Value::Int(-5)));
+        assert_eq!(apply_operation("foo", Value::Int(5), "^=", "10"), Ok(Value::Int(50)));
+        assert_eq!(apply_operation("foo", Value::Float(1.5), "+=", "1"), Ok(Value::Float(2.5)));
+        assert_eq!(apply_operation("foo", Value::Float(1.5), "-=", "1"), Ok(Value::Float(0.5)));
+        assert_eq!(apply_operation("foo", Value::Float(1.5), "^=", "2"), Ok(Value::Float(3.0)));
+        assert_eq!(apply_operation("foo", Value::Str("ab".to_string()), "+=", "cd"), Ok(Value::Str("abcd".to_string())));
+        assert_eq!(apply_operation("foo", Value::Str("ab".to_string()), "^=", "cd"), Ok(Value::Str("cdab".to_string())));
+        assert_eq!(apply_operation("foo", Value::Str("ab".to_string()), "-=", "cd"),
+            Err("Cannot subtract from foo: -= only works on numbers".to_string()));
+        assert_eq!(apply_operation("foo", Value::Int(5), "+=", "bar"),
+            Err("Cannot change foo with bar: expecting an integer".to_string()));
+        assert_eq!(apply_operation("foo", Value::Int(i64::MAX), "+=", "1"),
+            Err("Cannot change foo: the result is too large".to_string()));
+        assert_eq!(apply_operation("foo", Value::Bool(true), "+=", "1"),
+            Err("Cannot change foo with +=: it is a bool, use foo! instead".to_string()));
+    }
+
+    #[test]
+    fn test_toggle() {
+        assert_eq!(toggle("foo", Value::Bool(true)), Ok(Value::Bool(false)));
+        assert_eq!(toggle("foo", Value::Bool(false)), Ok(Value::Bool(true)));
+        assert_eq!(toggle("foo", Value::Int(1)), Err("Cannot toggle foo: expecting a bool setting".to_string()));
+        assert_eq!(parse_value("bool", "true").and_then(|value| toggle("foo", value)), Ok(Value::Bool(false)));
+        assert!(parse_value("bool", "yes").is_err());
+    }
+}
@@ -21,9 +21,11 @@
 
 use std::collections::HashMap;
 use std::io;
+use std::mem;
 use std::path::PathBuf;
 
 use mg_settings::{EnumMetaData, MetaData, SettingCompletion, Value};
+use mg_settings::Value::{Bool, Float, Int, Str};
 use mg_settings::errors::{Error, Result, SettingError};
 use mg_settings::settings;
 
@@ -65,3 +67,53 @@ impl SettingCompletion for NoSettings {
         HashMap::new()
     }
 }
+
+/// Get the type of the setting, or None if there is no setting with this name.
+/// The settings do not expose their types, so it is found by trying to convert a value for this
+/// setting.
+pub fn setting_type<T: settings::Settings>(name: &str) -> Option<String> {
+    match T::to_variant(name, Bool(false)) {
+        Ok(_) => Some("bool".to_string()),
+        Err(Error::Setting(SettingError::WrongType { expected, .. })) => Some(expected),
+        Err(_) => None,
+    }
+}
+
+/// Get the name of the setting changed by the variant, or None if it cannot be found.
+/// The variants do not expose their setting, so it is found by comparing the variant with a
+/// variant created for each setting, since the settings derive creates one enum variant per
+/// setting.
+pub fn variant_setting_name<T>(variant: &T::Variant) -> Option<String>
+    where T: EnumMetaData + SettingCompletion + settings::Settings,
+{
+    let discriminant = mem::discriminant(variant);
+    let value_completions = T::get_value_completions();
+    T::get_metadata().keys()
+        .find(|name| {
+            let mut values =
+                match setting_type::<T>(name).as_deref() {
+                    Some("bool") => vec![Bool(false)],
+                    Some("float") => vec![Float(0.0)],
+                    Some("integer") => vec![Int(0)],
+                    _ => vec![Str(String::new())],
+                };
+            // The settings with a custom type might only accept the completed values.
+            if let Some(completions) = value_completions.get(*name) {
+                values.extend(completions.iter().map(|value| Str(value.clone())));
+            }
+            values.into_iter()
+                .filter_map(|value| T::to_variant(name, value).ok())
+                .any(|probe| mem::discriminant(&probe) == discriminant)
+        })
+        .cloned()
+}
+
+/// Convert a value to the text used to set it in a command.
+pub fn value_to_string(value: &Value) -> String {
+    match *value {
+        Bool(value) => value.to_string(),
+        Float(value) => value.to_string(),
+        Int(value) => value.to_string(),
+        Str(ref value) => value.clone(),
+    }
+}
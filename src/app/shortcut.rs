@@ -115,8 +115,7 @@ impl<COMM, SETT> Mg<COMM, SETT>
                     if current_mode == INPUT_MODE || current_mode == BLOCKING_INPUT_MODE {
                         current_mode = COMMAND_MODE.to_string();
                     }
-                    self.model.mappings.get(&current_mode.as_ref())
-                        .and_then(|mappings| mappings.get(self.shortcut_without_prefix()).cloned())
+                    self.find_mapping(&current_mode, self.shortcut_without_prefix())
                 };
                 if let Some(action) = action {
                     let prefix = self.shortcut_prefix();
@@ -151,8 +150,8 @@ impl<COMM, SETT> Mg<COMM, SETT>
 
     /// Check if there are no possible shortcuts.
     fn no_possible_shortcut(&self) -> bool {
-        if let Some(mappings) = self.model.mappings.get(&self.model.mode_string.as_ref()) {
-            let shortcut = self.shortcut_without_prefix();
+        let shortcut = self.shortcut_without_prefix();
+        for mappings in self.mode_mappings(&self.model.mode_string) {
             for key in mappings.keys() {
                 if key.starts_with(shortcut) {
                     return false;
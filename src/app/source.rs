@@ -0,0 +1,409 @@
+/*
+ * Copyright (c) 2021 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Loading of the config layers and of config files at runtime, with the source command or when
+//! they change on disk.
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::mem;
+use std::path::{Path, PathBuf};
+
+use gtk::gio::{self, FileMonitorEvent, FileMonitorFlags};
+use gtk::gio::prelude::{FileExt, FileMonitorExt};
+use mg_settings::{EnumFromStr, EnumMetaData, ParseResult, SettingCompletion, SpecialCommand};
+use mg_settings::errors::Error;
+use mg_settings::settings;
+use relm::timeout;
+
+use app::Mg;
+use app::Msg::{ConfigFileChanged, ReloadConfig};
+
+/// The delay, in milliseconds, to wait for the other changes of the config files before reloading
+/// them, since editors often save a file in several steps.
+const CONFIG_RELOAD_DELAY: u32 = 200;
+
+/// The origin of the settings and mappings changed at runtime.
+pub const RUNTIME_ORIGIN: &str = "runtime";
+
+/// The name of the layer of the config file given when creating `Mg`.
+pub const USER_LAYER: &str = "user";
+
+/// A config loaded with the others, in order, each one overriding the previous ones.
+/// The name of the layer is reported as the origin of the settings and mappings it contains.
+#[derive(Clone)]
+pub struct ConfigLayer {
+    name: String,
+    source: ConfigSource,
+}
+
+#[derive(Clone)]
+enum ConfigSource {
+    File(PathBuf),
+    Text(String),
+    User,
+}
+
+impl ConfigLayer {
+    /// Create a layer loading the config file.
+    pub fn file<P: Into<PathBuf>>(name: &str, path: P) -> Self {
+        ConfigLayer {
+            name: name.to_string(),
+            source: ConfigSource::File(path.into()),
+        }
+    }
+
+    /// Create a layer loading the config from a reader, which is read right away.
+    pub fn reader<R: BufRead>(name: &str, mut reader: R) -> io::Result<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Ok(Self::text(name, &text))
+    }
+
+    /// Create a layer loading the config from a string, for instance a default config embedded in
+    /// the application with `include_str!()`.
+    pub fn text(name: &str, text: &str) -> Self {
+        ConfigLayer {
+            name: name.to_string(),
+            source: ConfigSource::Text(text.to_string()),
+        }
+    }
+
+    /// Create the layer of the config file given when creating `Mg`, named "user".
+    /// If the layers do not contain it, it is loaded first.
+    pub fn user() -> Self {
+        ConfigLayer {
+            name: USER_LAYER.to_string(),
+            source: ConfigSource::User,
+        }
+    }
+
+    /// Get the name of the layer.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Get the file included by the line, if it is an include command, with its column.
+/// The include commands with other arguments are left to the parser, which reports the error.
+fn included_file(line: &str) -> Option<(&str, usize)> {
+    let mut words = line.split_whitespace();
+    if words.next() != Some("include") {
+        return None;
+    }
+    let file = words.next()?;
+    if words.next().is_some() {
+        return None;
+    }
+    // The file is the last word of the line.
+    let column = line.trim_end().len() - file.len() + 1;
+    Some((file, column))
+}
+
+impl<COMM, SETT> Mg<COMM, SETT>
+where COMM: Clone + EnumFromStr + EnumMetaData + SpecialCommand + 'static,
+      SETT: Default + EnumMetaData + settings::Settings + SettingCompletion + 'static,
+{
+    /// Add the config file as the last config layer, named by its path, unless a layer already
+    /// loads it.
+    pub fn add_config_file(&mut self, path: &Path) {
+        let path = path.to_path_buf();
+        let loaded = self.model.config_layers.iter()
+            .chain(&self.model.sourced_layers)
+            .any(|layer| self.layer_path(layer).as_ref() == Some(&path));
+        if !loaded {
+            let layer = ConfigLayer::file(&path.display().to_string(), path);
+            self.model.config_layers.push(layer);
+            if self.model.watch_config {
+                self.watch_config(true);
+            }
+        }
+    }
+
+    /// Get the config files of the layers and the files they include, recursively.
+    fn config_files(&self) -> Vec<PathBuf> {
+        let include_path = self.model.include_path.clone().unwrap_or_else(|| PathBuf::from("./"));
+        let mut files = vec![];
+        let mut visited = HashSet::new();
+        let mut to_visit: Vec<PathBuf> = self.model.config_layers.iter()
+            .chain(&self.model.sourced_layers)
+            .rev()
+            .filter_map(|layer| self.layer_path(layer))
+            .collect();
+        while let Some(path) = to_visit.pop() {
+            if !visited.insert(path.clone()) {
+                continue;
+            }
+            if let Ok(file) = File::open(&path) {
+                for line in BufReader::new(file).lines().map_while(Result::ok) {
+                    let mut words = line.split_whitespace();
+                    if words.next() == Some("include") {
+                        if let Some(included_file) = words.next() {
+                            to_visit.push(include_path.join(included_file));
+                        }
+                    }
+                }
+            }
+            files.push(path);
+        }
+        files
+    }
+
+    /// Schedule a reload of the config after one of its files changed.
+    pub fn config_file_changed(&mut self) {
+        if !mem::replace(&mut self.model.reload_pending, true) {
+            timeout(self.model.relm.stream(), CONFIG_RELOAD_DELAY, || ReloadConfig);
+        }
+    }
+
+    /// Execute the commands from the config `source`, remembering that the settings and mappings
+    /// come from `origin`.
+    /// The errors are added to the config errors.
+    pub fn execute_config(&mut self, mut parse_result: ParseResult<COMM>, origin: &str, source: &str) {
+        for error in parse_result.errors.drain(..) {
+            self.add_config_error(source, &error);
+        }
+        let origin = mem::replace(&mut self.model.current_origin, origin.to_string());
+        let config_source = self.model.config_source.replace(source.to_string());
+        // The commands from a config are not changes to write back to the config.
+        let record_overrides = mem::replace(&mut self.model.record_overrides, false);
+        self.execute_commands(parse_result, false);
+        self.model.record_overrides = record_overrides;
+        self.model.config_source = config_source;
+        self.model.current_origin = origin;
+    }
+
+    /// Get the path of the config file of the layer.
+    fn layer_path(&self, layer: &ConfigLayer) -> Option<PathBuf> {
+        match layer.source {
+            ConfigSource::File(ref path) => Some(path.clone()),
+            ConfigSource::Text(_) => None,
+            ConfigSource::User => self.model.config_path.clone(),
+        }
+    }
+
+    /// Load the config layers a first time, reporting the errors found when creating the widget.
+    pub fn load_initial_config(&mut self) {
+        self.model.config_loaded = true;
+        self.load_config_layers();
+        let source = self.model.config_path.as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| USER_LAYER.to_string());
+        let errors: Vec<_> = self.model.initial_errors.drain(..).collect();
+        for error in errors {
+            self.add_config_error(&source, &error);
+        }
+        if self.model.watch_config {
+            self.watch_config(true);
+        }
+        // The next changes are done at runtime.
+        self.model.record_overrides = true;
+        self.report_config_errors(0);
+    }
+
+    /// Load the config layers in order, then the files sourced at runtime: the mappings are
+    /// replaced by the ones from the layers and the changes done at runtime are applied again.
+    /// The config errors are replaced by the ones found in the layers.
+    fn load_config_layers(&mut self) {
+        self.model.config_errors.clear();
+        self.model.config_errors_changed = true;
+        self.model.mappings.clear();
+        self.model.context_mappings.clear();
+        self.model.mapping_origins.clear();
+        self.model.mappings_changed = true;
+        let layers: Vec<_> = self.model.config_layers.iter()
+            .chain(&self.model.sourced_layers)
+            .cloned()
+            .collect();
+        for layer in layers {
+            if let ConfigSource::Text(ref text) = layer.source {
+                self.source_reader(text.as_bytes(), &layer.name);
+            }
+            else if let Some(path) = self.layer_path(&layer) {
+                // The system and project configs are optional.
+                if path.exists() {
+                    self.source_file(&path, &layer.name);
+                }
+            }
+        }
+        for command in self.override_commands() {
+            let parse_result = self.model.settings_parser.parse_line(&command, None);
+            self.execute_config(parse_result, RUNTIME_ORIGIN, RUNTIME_ORIGIN);
+        }
+    }
+
+    /// Reload the config layers.
+    pub fn reload_config(&mut self) {
+        self.model.reload_pending = false;
+        self.load_config_layers();
+        // The includes might have changed.
+        if self.model.watch_config {
+            self.watch_config(true);
+        }
+        if self.report_config_errors(0) {
+            self.info("Config reloaded");
+        }
+    }
+
+    /// Set the config layers and load them.
+    pub fn set_config_layers(&mut self, mut layers: Vec<ConfigLayer>) {
+        if !layers.iter().any(|layer| matches!(layer.source, ConfigSource::User)) {
+            layers.insert(0, ConfigLayer::user());
+        }
+        self.model.config_layers = layers;
+        // Before the initial load, the layers will be loaded by it.
+        if !self.model.config_loaded {
+            return;
+        }
+        self.load_config_layers();
+        if self.model.watch_config {
+            self.watch_config(true);
+        }
+        self.report_config_errors(0);
+    }
+
+    /// Execute the commands of a config file.
+    /// Return false if there were errors, which are added to the config errors.
+    pub fn source_file(&mut self, path: &Path, origin: &str) -> bool {
+        let source = path.display().to_string();
+        let file =
+            match File::open(path) {
+                Ok(file) => file,
+                Err(error) => {
+                    self.add_config_error(&source, &Error::Msg(format!("Cannot open the config file: {}", error)));
+                    return false;
+                },
+            };
+        self.source_config(BufReader::new(file), origin, &source)
+    }
+
+    /// Execute the commands of a config file sourced at runtime, named `origin`, and remember it to
+    /// load it again after the config layers when the config is reloaded.
+    /// Return false if there were errors, which are added to the config errors.
+    pub fn source_runtime_file(&mut self, path: &Path, origin: &str) -> bool {
+        if path.exists() {
+            // A file sourced again is now loaded after the others.
+            self.model.sourced_layers.retain(|layer|
+                !matches!(layer.source, ConfigSource::File(ref file) if file == path));
+            self.model.sourced_layers.push(ConfigLayer::file(origin, path));
+            if self.model.watch_config {
+                self.watch_config(true);
+            }
+        }
+        self.source_file(path, origin)
+    }
+
+    /// Execute the commands of a config read from `reader`, named `origin`.
+    /// Return false if there were errors, which are added to the config errors.
+    pub fn source_reader<R: BufRead>(&mut self, reader: R, origin: &str) -> bool {
+        self.source_config(reader, origin, origin)
+    }
+
+    /// Execute the commands of the config, the included files being sourced at the position of
+    /// their include.
+    /// The parser would report the errors of an included file with its lines, but without its
+    /// name, so the includes are sourced here to use the included file as the source of its
+    /// errors.
+    fn source_config<R: BufRead>(&mut self, reader: R, origin: &str, source: &str) -> bool {
+        let first_error = self.model.config_errors.len();
+        // The commands before an include, preceded by empty lines to keep their line numbers.
+        let mut commands = String::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line =
+                match line {
+                    Ok(line) => line,
+                    Err(error) => {
+                        self.add_config_error(source, &error.into());
+                        String::new()
+                    },
+                };
+            match included_file(&line) {
+                Some((included_file, column)) => {
+                    let parse_result = self.model.settings_parser.parse(commands.as_bytes(), None);
+                    self.execute_config(parse_result, origin, source);
+                    commands = "\n".repeat(index + 1);
+                    self.source_include(included_file, (index + 1, column), origin, source);
+                },
+                None => {
+                    commands.push_str(&line);
+                    commands.push('\n');
+                },
+            }
+        }
+        let parse_result = self.model.settings_parser.parse(commands.as_bytes(), None);
+        self.execute_config(parse_result, origin, source);
+        self.model.config_errors.len() == first_error
+    }
+
+    /// Execute the commands of a file included at `position` in the config `source`.
+    fn source_include(&mut self, file: &str, position: (usize, usize), origin: &str, source: &str) {
+        let include_path = self.model.include_path.clone().unwrap_or_else(|| PathBuf::from("./"));
+        let path = include_path.join(file);
+        match File::open(&path) {
+            Ok(included_file) => {
+                self.source_config(BufReader::new(included_file), origin, &path.display().to_string());
+            },
+            Err(error) => self.add_config_error_at(source, position,
+                &format!("Cannot open the included file {}: {}", path.display(), error)),
+        }
+    }
+
+    /// Start or stop reloading the config when it or one of the files it includes changes.
+    pub fn watch_config(&mut self, watch: bool) {
+        // Dropping the monitors stops them.
+        self.model.config_monitors.clear();
+        self.model.watch_config = watch;
+        if !watch {
+            return;
+        }
+        for path in self.config_files() {
+            let file = gio::File::for_path(&path);
+            match file.monitor_file(FileMonitorFlags::NONE, None::<&gio::Cancellable>) {
+                Ok(monitor) => {
+                    let stream = self.model.relm.stream().clone();
+                    monitor.connect_changed(move |_, _, _, event| {
+                        if event == FileMonitorEvent::ChangesDoneHint || event == FileMonitorEvent::Created {
+                            stream.emit(ConfigFileChanged);
+                        }
+                    });
+                    self.model.config_monitors.push(monitor);
+                },
+                Err(error) => self.warning(&format!("Cannot watch the config file {}: {}", path.display(), error)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::included_file;
+
+    #[test]
+    fn test_included_file() {
+        assert_eq!(included_file("include foo.conf"), Some(("foo.conf", 9)));
+        assert_eq!(included_file("  include   foo.conf  "), Some(("foo.conf", 13)));
+        assert_eq!(included_file("include"), None);
+        assert_eq!(included_file("include foo bar"), None);
+        assert_eq!(included_file("set include = foo"), None);
+        assert_eq!(included_file("# include foo"), None);
+    }
+}
@@ -39,7 +39,7 @@ use gtk::{
     STYLE_PROVIDER_PRIORITY_APPLICATION,
 };
 use gtk::Orientation::Horizontal;
-use pango::EllipsizeMode;
+use pango::{AttrInt, AttrList, EllipsizeMode};
 use relm::{Relm, Widget};
 use relm_derive::widget;
 
@@ -57,6 +57,7 @@ pub enum Msg {
     End,
     EntryActivate(String),
     EntryChanged(String),
+    EntryCursorMoved(i32),
     EntryText(String),
     EntryShown(bool),
     Identifier(String),
@@ -68,8 +69,11 @@ pub enum Msg {
     PreviousWord,
     ShowIdentifier,
     SmartHome,
+    Suggestion(String),
 }
 
+const SUGGESTION_ALPHA: u16 = 0x8000;
+
 pub struct Model {
     identifier_label: &'static str,
     identifier_visible: bool,
@@ -95,6 +99,10 @@ impl Widget for StatusBar {
         let provider = CssProvider::new();
         provider.load_from_data(style).unwrap();
         style_context.add_provider(&provider, STYLE_PROVIDER_PRIORITY_APPLICATION);
+
+        let attributes = AttrList::new();
+        attributes.insert(AttrInt::new_foreground_alpha(SUGGESTION_ALPHA));
+        self.widgets.suggestion_label.set_attributes(Some(&attributes));
     }
 
     fn model(relm: &Relm<Self>, _: ()) -> Model {
@@ -133,7 +141,8 @@ impl Widget for StatusBar {
             DeleteNextWord => self.delete_next_word(),
             DeletePreviousWord => self.delete_previous_word(),
             End => self.end(),
-            EntryActivate(_) | EntryChanged(_) => (), // NOTE: to be listened by the user.
+            // NOTE: to be listened by the user.
+            EntryActivate(_) | EntryChanged(_) | EntryCursorMoved(_) => (),
             EntryShown(visible) => self.set_entry_shown(visible),
             EntryText(input) => self.set_input(&input),
             Identifier(identifier) => self.set_identifier(&identifier),
@@ -145,6 +154,7 @@ impl Widget for StatusBar {
             PreviousWord => self.previous_word(),
             ShowIdentifier => self.show_identifier(),
             SmartHome => self.smart_home(),
+            Suggestion(suggestion) => self.set_suggestion(&suggestion),
         }
     }
 
@@ -164,10 +174,18 @@ impl Widget for StatusBar {
             gtk::Entry {
                 activate(entry) => EntryActivate(entry.text().to_string()),
                 changed(entry) => EntryChanged(entry.text().to_string()),
+                cursor_position_notify(entry) => EntryCursorMoved(entry.position()),
                 has_frame: false,
                 hexpand: true,
                 widget_name: "mg-input-command",
             },
+            #[name="suggestion_label"]
+            gtk::Label {
+                ellipsize: EllipsizeMode::End,
+                hexpand: true,
+                no_show_all: true,
+                xalign: 0.0,
+            },
         }
     }
 }
@@ -347,6 +365,31 @@ impl StatusBar {
         self.widgets.command_entry.set_position(command.chars().count() as i32);
     }
 
+    /// Show the suggestion as dimmed text after the text of the entry.
+    fn set_suggestion(&self, suggestion: &str) {
+        let entry = &self.widgets.command_entry;
+        if suggestion.is_empty() {
+            entry.set_hexpand(true);
+            entry.set_width_chars(-1);
+            entry.set_size_request(-1, -1);
+            self.widgets.suggestion_label.set_visible(false);
+        }
+        else {
+            // The entry only takes the width of its text, measured from its layout to support
+            // proportional fonts, so that the suggestion is shown right after it.
+            let (offset, _) = entry.layout_offsets();
+            let text_width = entry.layout()
+                .map(|layout| layout.pixel_size().0)
+                .unwrap_or_default();
+            entry.set_hexpand(false);
+            entry.set_width_chars(0);
+            // One more pixel for the cursor.
+            entry.set_size_request(offset + text_width + 1, -1);
+            self.widgets.suggestion_label.set_text(suggestion);
+            self.widgets.suggestion_label.set_visible(true);
+        }
+    }
+
     /// Go to the beginning of the command entry.
     /// If the cursor is already at the beginning, go after the spaces after the command name.
     fn smart_home(&self) {
@@ -0,0 +1,100 @@
+/*
+ * Copyright (c) 2021 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Inline suggestion shown after the text of the command entry.
+
+use mg_settings::{EnumFromStr, EnumMetaData, SettingCompletion, SpecialCommand};
+use mg_settings::settings;
+
+use app::{Mg, Mode};
+use app::status_bar::Msg::End;
+
+const COMMAND_HISTORY_SIZE: usize = 1000;
+
+impl<COMM, SETT> Mg<COMM, SETT>
+where COMM: Clone + EnumFromStr + EnumMetaData + SpecialCommand + 'static,
+      SETT: Default + EnumMetaData + settings::Settings + SettingCompletion + 'static,
+{
+    /// Add the command to the history, which is used for the suggestions.
+    pub fn add_to_history(&mut self, command: &str) {
+        let command = command.trim();
+        if command.is_empty() {
+            return;
+        }
+        self.model.command_history.retain(|history_command| history_command != command);
+        self.model.command_history.push(command.to_string());
+        if self.model.command_history.len() > COMMAND_HISTORY_SIZE {
+            self.model.command_history.remove(0);
+        }
+    }
+
+    /// Complete the input with the suggestion or go to the end of the entry if there's none.
+    pub fn accept_suggestion(&mut self) {
+        if self.model.status_bar_suggestion.is_empty() {
+            self.streams.status_bar.emit(End);
+        }
+        else {
+            let input = format!("{}{}", self.model.status_bar_command, self.model.status_bar_suggestion);
+            self.set_input(&input);
+            self.update_completions();
+        }
+    }
+
+    /// Clear the suggestion.
+    pub fn clear_suggestion(&mut self) {
+        if !self.model.status_bar_suggestion.is_empty() {
+            self.set_status_bar_suggestion(String::new());
+        }
+    }
+
+    /// Set the input completed with the first result of the completer.
+    pub fn set_completion_suggestion(&mut self, completion: Option<String>) {
+        self.model.completion_suggestion = completion;
+        self.update_suggestion();
+    }
+
+    /// Update the suggestion for the current input.
+    /// The most recent command of the history starting with the input is suggested, or else the
+    /// first result of the completer.
+    /// There's no suggestion when the cursor is not at the end of the input.
+    pub fn update_suggestion(&mut self) {
+        let input = &self.model.status_bar_command;
+        let is_at_end = !matches!(self.model.status_bar_cursor, Some(cursor) if cursor < input.chars().count());
+        let suggestion =
+            if self.model.current_mode.get() != Mode::Command || !is_at_end || input.trim().is_empty() {
+                None
+            }
+            else {
+                self.model.command_history.iter().rev()
+                    .chain(self.model.completion_suggestion.iter())
+                    .find(|suggestion| suggestion.len() > input.len() && suggestion.starts_with(input.as_str()))
+                    .map(|suggestion| suggestion[input.len()..].to_string())
+            };
+        match suggestion {
+            Some(suggestion) => {
+                if suggestion != self.model.status_bar_suggestion {
+                    self.set_status_bar_suggestion(suggestion);
+                }
+            },
+            None => self.clear_suggestion(),
+        }
+    }
+}
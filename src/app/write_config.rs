@@ -0,0 +1,376 @@
+/*
+ * Copyright (c) 2021 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Writing of the settings and mappings changed at runtime back to a config file.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use mg_settings::{EnumFromStr, EnumMetaData, SettingCompletion, SpecialCommand};
+use mg_settings::errors::Error;
+use mg_settings::key::Key;
+use mg_settings::settings;
+
+use app::Mg;
+use app::context::CONTEXT_OPTION;
+
+/// The comment starting the block of commands written by write-config.
+const BLOCK_HEADER: &str = "# Written by write-config.";
+
+/// A mapping created or removed at runtime.
+pub struct MappingOverride {
+    /// The action or None if the mapping was removed.
+    action: Option<String>,
+    context: Option<String>,
+    keys: String,
+    mode: String,
+}
+
+impl MappingOverride {
+    /// Get the command recreating this change.
+    fn to_command(&self) -> String {
+        match self.action {
+            Some(ref action) => {
+                let context = self.context.as_ref()
+                    .map(|context| format!("{}{} ", CONTEXT_OPTION, context))
+                    .unwrap_or_default();
+                format!("{}map {}{} {}", self.mode, context, self.keys, action)
+            },
+            None => format!("{}unmap {}", self.mode, self.keys),
+        }
+    }
+}
+
+/// What a command written by write-config changes.
+#[derive(Debug, PartialEq)]
+enum CommandTarget {
+    Mapping {
+        context: Option<String>,
+        keys: String,
+        mode: String,
+    },
+    Setting(String),
+    Unmapping {
+        keys: String,
+        mode: String,
+    },
+}
+
+impl CommandTarget {
+    /// Get what the command changes, or None if it is not a set, map or unmap command.
+    fn new(command: &str) -> Option<Self> {
+        let mut words = command.split_whitespace();
+        let name = words.next()?;
+        if name == "set" {
+            let setting = words.next()?;
+            return Some(CommandTarget::Setting(setting.trim_end_matches('=').to_string()));
+        }
+        if let Some(mode) = name.strip_suffix("unmap") {
+            return Some(CommandTarget::Unmapping {
+                keys: words.next()?.to_string(),
+                mode: mode.to_string(),
+            });
+        }
+        let mode = name.strip_suffix("map")?;
+        let mut keys = words.next()?;
+        let context = keys.strip_prefix(CONTEXT_OPTION).map(ToString::to_string);
+        if context.is_some() {
+            keys = words.next()?;
+        }
+        Some(CommandTarget::Mapping {
+            context,
+            keys: keys.to_string(),
+            mode: mode.to_string(),
+        })
+    }
+
+    /// Check whether a command changing this target makes a previous command changing `previous`
+    /// useless.
+    /// An unmap command removes the mapping from every context, while a map command only replaces
+    /// the mapping of its context.
+    fn overrides(&self, previous: &CommandTarget) -> bool {
+        match (self, previous) {
+            (&CommandTarget::Unmapping { ref keys, ref mode },
+             &CommandTarget::Mapping { keys: ref previous_keys, mode: ref previous_mode, .. }) |
+            (&CommandTarget::Unmapping { ref keys, ref mode },
+             &CommandTarget::Unmapping { keys: ref previous_keys, mode: ref previous_mode }) =>
+                keys == previous_keys && mode == previous_mode,
+            _ => self == previous,
+        }
+    }
+}
+
+/// Get the commands recreating the changes, the settings first.
+/// The settings whose values are unknown are skipped.
+fn config_commands(settings: &BTreeMap<String, Option<String>>, mappings: &[MappingOverride]) -> Vec<String> {
+    settings.iter()
+        .filter_map(|(name, value)| value.as_ref().map(|value| format!("set {} = {}", name, value)))
+        .chain(mappings.iter().map(MappingOverride::to_command))
+        .collect()
+}
+
+/// Add the commands to the block written by write-config in the content of a config file, or to a
+/// new block at its end.
+/// The commands of the block which are overridden by the new ones are removed and the rest of the
+/// content is kept as is.
+fn merge_block(content: &str, commands: &[String]) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let (before, block, after) =
+        match lines.iter().position(|line| line.trim() == BLOCK_HEADER) {
+            Some(start) => {
+                // The block ends at the first empty line.
+                let end = lines[start + 1..].iter()
+                    .position(|line| line.trim().is_empty())
+                    .map(|index| start + 1 + index)
+                    .unwrap_or_else(|| lines.len());
+                (&lines[..start], &lines[start + 1..end], &lines[end..])
+            },
+            None => (&lines[..], &[][..], &[][..]),
+        };
+    let mut block_commands: Vec<String> = block.iter().map(ToString::to_string).collect();
+    for command in commands {
+        if let Some(target) = CommandTarget::new(command) {
+            block_commands.retain(|previous| !CommandTarget::new(previous)
+                .is_some_and(|previous| target.overrides(&previous)));
+        }
+        block_commands.push(command.clone());
+    }
+    let mut result = String::new();
+    for line in before {
+        result.push_str(line);
+        result.push('\n');
+    }
+    if before.last().is_some_and(|line| !line.trim().is_empty()) {
+        result.push('\n');
+    }
+    result.push_str(BLOCK_HEADER);
+    result.push('\n');
+    for line in block_commands.iter().map(String::as_str).chain(after.iter().cloned()) {
+        result.push_str(line);
+        result.push('\n');
+    }
+    result
+}
+
+/// Add the commands to the block written by write-config in the file, creating it if needed.
+fn write_commands(path: &Path, commands: &[String]) -> io::Result<()> {
+    let content =
+        match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(ref error) if error.kind() == ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error),
+        };
+    fs::write(path, merge_block(&content, commands))
+}
+
+impl<COMM, SETT> Mg<COMM, SETT>
+where COMM: Clone + EnumFromStr + EnumMetaData + SpecialCommand + 'static,
+      SETT: Default + EnumMetaData + settings::Settings + SettingCompletion + 'static,
+{
+    /// Get the commands recreating the changes done at runtime.
+    pub fn override_commands(&self) -> Vec<String> {
+        config_commands(&self.model.setting_overrides, &self.model.mapping_overrides)
+    }
+
+    /// Remember a mapping change done at runtime, so that it can be written to the config.
+    pub fn record_mapping_override(&mut self, mode: &str, context: Option<&str>, keys: &[Key],
+        action: Option<&str>)
+    {
+        if !self.model.record_overrides {
+            return;
+        }
+        let keys: String = keys.iter().map(ToString::to_string).collect();
+        // An unmap command removes the mapping from every context.
+        self.model.mapping_overrides.retain(|mapping| mapping.mode != mode || mapping.keys != keys ||
+            (action.is_some() && mapping.context.as_deref() != context));
+        self.model.mapping_overrides.push(MappingOverride {
+            action: action.map(ToString::to_string),
+            context: context.map(ToString::to_string),
+            keys,
+            mode: mode.to_string(),
+        });
+    }
+
+    /// Remember a setting change done at runtime, so that it can be written to the config.
+    /// The value is None when the setting was changed with SetSetting, since it cannot be read.
+    pub fn record_setting_override(&mut self, name: &str, value: Option<&str>) {
+        if self.model.record_overrides {
+            self.model.setting_overrides.insert(name.to_string(), value.map(ToString::to_string));
+        }
+    }
+
+    /// Write the settings and mappings changed at runtime to the file, or to the config file if
+    /// there's no file.
+    /// The commands replace the ones previously written by write-config in this file, and the rest
+    /// of the file is kept, including its comments and includes. A file other than the config file
+    /// is then loaded with the config layers, so that the changes are kept when the config is
+    /// reloaded; the application needs to add it to its config layers to load it at startup.
+    /// The settings changed with SetSetting cannot be written since their values are unknown:
+    /// SetSettingValue should be used instead for the settings to write.
+    pub fn write_config(&mut self, path: Option<PathBuf>) {
+        let unknown_settings: Vec<_> = self.model.setting_overrides.iter()
+            .filter(|(_, value)| value.is_none())
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let unknown_warning =
+            if unknown_settings.is_empty() {
+                None
+            }
+            else {
+                Some(format!("Not written since their values are unknown: {}", unknown_settings.join(", ")))
+            };
+        let commands = self.override_commands();
+        if commands.is_empty() {
+            match unknown_warning {
+                Some(warning) => self.warning(&warning),
+                None => self.info("No changes to write"),
+            }
+            return;
+        }
+        let path =
+            match path.or_else(|| self.model.config_path.clone()) {
+                Some(path) => path,
+                None => {
+                    self.error(Error::Msg("No config file to write to".to_string()));
+                    return;
+                },
+            };
+        match write_commands(&path, &commands) {
+            Ok(()) => {
+                // The changes are now in the file.
+                self.model.mapping_overrides.clear();
+                self.model.setting_overrides.clear();
+                self.add_config_file(&path);
+                match unknown_warning {
+                    Some(warning) => self.warning(&format!("Config written to {}. {}", path.display(), warning)),
+                    None => self.info(&format!("Config written to {}", path.display())),
+                }
+            },
+            Err(error) => self.error(Error::Msg(format!("Cannot write the config to {}: {}", path.display(), error))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use mg_settings::{Command, Config, EnumFromStr, Parser, Value};
+    use mg_settings::key::Key::{Char, Control};
+
+    use app::context::parse_context_mapping;
+    use super::{MappingOverride, config_commands, merge_block};
+
+    #[derive(Debug, PartialEq)]
+    enum NoCommand {}
+
+    impl EnumFromStr for NoCommand {
+        fn create(variant: &str, _argument: &str, _prefix: Option<u32>) -> Result<Self, String> {
+            Err(format!("unknown command {}", variant))
+        }
+
+        fn has_argument(variant: &str) -> Result<bool, String> {
+            Err(format!("unknown command {}", variant))
+        }
+    }
+
+    fn parse(command: &str) -> Command<NoCommand> {
+        let mut parser = Parser::new_with_config(Config {
+            application_commands: vec![],
+            mapping_modes: vec!["n"],
+        });
+        let mut result = parser.parse_line(command, None);
+        assert!(result.errors.is_empty(), "cannot parse {}", command);
+        assert_eq!(result.commands.len(), 1);
+        result.commands.remove(0)
+    }
+
+    fn mapping(action: Option<&str>, context: Option<&str>) -> MappingOverride {
+        MappingOverride {
+            action: action.map(ToString::to_string),
+            context: context.map(ToString::to_string),
+            keys: "<C-a>b".to_string(),
+            mode: "n".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_mapping_override_to_command() {
+        match parse(&mapping(Some("open foo"), None).to_command()) {
+            Command::Map { action, keys, mode } => {
+                assert_eq!(parse_context_mapping(keys, action).unwrap(),
+                    (None, vec![Control(Box::new(Char('a'))), Char('b')], "open foo".to_string()));
+                assert_eq!(mode, "n");
+            },
+            command => panic!("unexpected command {:?}", command),
+        }
+
+        match parse(&mapping(Some("open foo"), Some("editor")).to_command()) {
+            Command::Map { action, keys, mode } => {
+                assert_eq!(parse_context_mapping(keys, action).unwrap(),
+                    (Some("editor".to_string()), vec![Control(Box::new(Char('a'))), Char('b')],
+                        "open foo".to_string()));
+                assert_eq!(mode, "n");
+            },
+            command => panic!("unexpected command {:?}", command),
+        }
+
+        assert_eq!(parse(&mapping(None, None).to_command()), Command::Unmap {
+            keys: vec![Control(Box::new(Char('a'))), Char('b')],
+            mode: "n".to_string(),
+        });
+    }
+
+    #[test]
+    fn test_config_commands() {
+        let mut settings = BTreeMap::new();
+        settings.insert("title".to_string(), Some("My title".to_string()));
+        settings.insert("unknown".to_string(), None);
+        settings.insert("width".to_string(), Some("42".to_string()));
+        let commands = config_commands(&settings, &[mapping(None, None)]);
+        assert_eq!(commands.len(), 3);
+        assert_eq!(parse(&commands[0]), Command::Set("title".to_string(), Value::Str("My title".to_string())));
+        assert_eq!(parse(&commands[1]), Command::Set("width".to_string(), Value::Int(42)));
+        assert_eq!(parse(&commands[2]), Command::Unmap {
+            keys: vec![Control(Box::new(Char('a'))), Char('b')],
+            mode: "n".to_string(),
+        });
+    }
+
+    #[test]
+    fn test_merge_block() {
+        let commands = vec!["set width = 42".to_string(), "nmap a open".to_string()];
+        assert_eq!(merge_block("", &commands), "# Written by write-config.\nset width = 42\nnmap a open\n");
+        assert_eq!(merge_block("set width = 10\n", &commands),
+            "set width = 10\n\n# Written by write-config.\nset width = 42\nnmap a open\n");
+
+        let content = "include foo\n\n# Written by write-config.\nset width = 10\nset title = \"a\"\nnmap a quit\n\
+            nmap --context=editor a quit\nnmap b quit\n\n# Comment\nset height = 2\n";
+        assert_eq!(merge_block(content, &commands),
+            "include foo\n\n# Written by write-config.\nset title = \"a\"\nnmap --context=editor a quit\n\
+            nmap b quit\nset width = 42\nnmap a open\n\n# Comment\nset height = 2\n");
+        assert_eq!(merge_block(content, &["nunmap a".to_string()]),
+            "include foo\n\n# Written by write-config.\nset width = 10\nset title = \"a\"\nnmap b quit\nnunmap a\n\n\
+            # Comment\nset height = 2\n");
+    }
+}
@@ -0,0 +1,192 @@
+/*
+ * Copyright (c) 2021 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Completers computing their results off the main thread.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use relm::Sender;
+
+use completion::{Completer, CompletionResult};
+use completion::Column::{self, Expand};
+
+/// A batch of completion results sent from a background thread.
+#[doc(hidden)]
+pub struct CompletionBatch {
+    /// The query generation for which the results were computed.
+    pub generation: usize,
+    /// The results, or None when all the results were sent.
+    pub results: Option<Vec<CompletionResult>>,
+}
+
+/// The trait async completer is an interface to be satisfied by completers which are too slow to
+/// run on the GTK thread, like the ones searching the filesystem or a database.
+/// Their results are computed in a background thread and streamed to the completion view.
+pub trait AsyncCompleter: Send + Sync {
+    /// The number of columns of the completer.
+    fn columns(&self) -> Vec<Column> {
+        vec![Expand, Expand]
+    }
+
+    /// The titles of the columns, shown as headers if the completion view is configured to show
+    /// them.
+    fn column_titles(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Return true to allow the complete-prefix command to extend the input to the longest common
+    /// prefix of the results.
+    fn complete_common_prefix(&self) -> bool {
+        false
+    }
+
+    /// From the selected text entry, return the text that should be written in the text input.
+    fn complete_result(&self, value: &str) -> String {
+        value.to_string()
+    }
+
+    /// From the user input, send the completion results to the sink.
+    /// The results can be sent in many batches and the completer should stop as soon as the sink
+    /// is cancelled, which happens when the user typed something else.
+    fn completions(&self, input: &str, sink: &CompletionSink);
+
+    /// Called when the user deletes a result from the completion view, with the values of its
+    /// columns.
+    fn delete(&self, _row: &[String]) {
+    }
+
+    /// Return true if the completer is for an input containing a command.
+    /// Return false otherwise.
+    fn have_command(&self) -> bool {
+        true
+    }
+
+    /// Set the column to use as the result of a selected text entry.
+    fn text_column(&self) -> i32 {
+        0
+    }
+}
+
+/// The destination of the results of an async completer.
+pub struct CompletionSink {
+    current_generation: Arc<AtomicUsize>,
+    generation: usize,
+    sender: Sender<CompletionBatch>,
+}
+
+impl CompletionSink {
+    /// Check if the query was replaced by a newer one, in which case the results are not needed
+    /// anymore.
+    pub fn is_cancelled(&self) -> bool {
+        self.current_generation.load(Ordering::SeqCst) != self.generation
+    }
+
+    /// Send a batch of results to the completion view.
+    /// Return false if the query was cancelled.
+    pub fn send(&self, results: Vec<CompletionResult>) -> bool {
+        if self.is_cancelled() {
+            return false;
+        }
+        self.sender.send(CompletionBatch {
+            generation: self.generation,
+            results: Some(results),
+        }).is_ok()
+    }
+
+    fn done(&self) {
+        let _ = self.sender.send(CompletionBatch {
+            generation: self.generation,
+            results: None,
+        });
+    }
+}
+
+/// Adapter to use an async completer where a `Completer` is expected.
+pub struct ThreadedCompleter {
+    completer: Arc<dyn AsyncCompleter>,
+}
+
+impl ThreadedCompleter {
+    /// Create a new adapter for the async completer.
+    pub fn new<C: AsyncCompleter + 'static>(completer: C) -> Self {
+        ThreadedCompleter {
+            completer: Arc::new(completer),
+        }
+    }
+}
+
+impl Completer for ThreadedCompleter {
+    fn async_completer(&self) -> Option<Arc<dyn AsyncCompleter>> {
+        Some(self.completer.clone())
+    }
+
+    fn column_titles(&self) -> Vec<String> {
+        self.completer.column_titles()
+    }
+
+    fn columns(&self) -> Vec<Column> {
+        self.completer.columns()
+    }
+
+    fn complete_common_prefix(&self) -> bool {
+        self.completer.complete_common_prefix()
+    }
+
+    fn complete_result(&self, value: &str) -> String {
+        self.completer.complete_result(value)
+    }
+
+    fn completions(&mut self, _input: &str) -> Vec<CompletionResult> {
+        vec![]
+    }
+
+    fn delete(&mut self, row: &[String]) {
+        self.completer.delete(row);
+    }
+
+    fn have_command(&self) -> bool {
+        self.completer.have_command()
+    }
+
+    fn text_column(&self) -> i32 {
+        self.completer.text_column()
+    }
+}
+
+/// Compute the completions of the async completer in a new thread.
+pub fn spawn_completions(completer: Arc<dyn AsyncCompleter>, input: String, current_generation: Arc<AtomicUsize>,
+    sender: Sender<CompletionBatch>)
+{
+    let generation = current_generation.load(Ordering::SeqCst);
+    thread::spawn(move || {
+        let sink = CompletionSink {
+            current_generation,
+            generation,
+            sender,
+        };
+        if !sink.is_cancelled() {
+            completer.completions(&input, &sink);
+        }
+        sink.done();
+    });
+}
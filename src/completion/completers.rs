@@ -19,12 +19,21 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs::read_dir;
 use std::marker::PhantomData;
+use std::path::Path;
 
 use mg_settings::{EnumMetaData, SettingCompletion};
+use mg_settings::settings::Settings;
 
-use completion::{Completer, CompletionResult};
+use Mode;
+use app::settings::setting_type;
+use file::expand_home;
+use completion::{Completer, CompletionCell, CompletionResult, fuzzy_completions};
+use completion::Column::{self, Expand};
+
+const DIRECTORY_FOREGROUND: &str = "#3465a4";
 
 /// A command completer.
 pub struct CommandCompleter<T: Clone> {
@@ -41,25 +50,150 @@ impl<T: Clone + EnumMetaData> CommandCompleter<T> {
                 .filter(|&(_, metadata)| !metadata.completion_hidden)
                 .map(|(setting_name, metadata)| (setting_name.clone(), metadata.help_text.clone()))
                 .collect();
+        data.push(("errors".to_string(), "List the errors found in the config".to_string()));
         data.push(("map".to_string(), "Create a new key binding".to_string()));
+        data.push(("messages".to_string(), "List the last messages".to_string()));
+        data.push(("mksettings".to_string(), "Write the changed settings and key bindings to the config".to_string()));
         data.push(("set".to_string(), "Change the value of a setting".to_string()));
+        data.push(("source".to_string(), "Execute the commands of a config file".to_string()));
         data.push(("unmap".to_string(), "Delete a key binding".to_string()));
+        data.push(("write-config".to_string(),
+            "Write the changed settings and key bindings to the config or to a file".to_string()));
         data.sort();
         CommandCompleter {
             metadata: data,
             _phantom: PhantomData,
         }
     }
+
+    /// Also complete these application commands.
+    pub fn application_commands(mut self, commands: &[&str]) -> Self {
+        self.metadata.extend(commands.iter().map(|command| (command.to_string(), String::new())));
+        self.metadata.sort();
+        self
+    }
+
+    /// Complete the map and unmap commands prefixed by these modes instead of the generic ones.
+    pub fn modes<'a, I: IntoIterator<Item=&'a Mode>>(mut self, modes: I) -> Self {
+        self.metadata.retain(|(command, _)| command != "map" && command != "unmap");
+        for mode in modes {
+            self.metadata.push((format!("{}map", mode.prefix),
+                format!("Create a new key binding in {} mode", mode.name)));
+            self.metadata.push((format!("{}unmap", mode.prefix),
+                format!("Delete a key binding in {} mode", mode.name)));
+        }
+        self.metadata.sort();
+        self
+    }
 }
 
 impl<T: Clone> Completer for CommandCompleter<T> {
+    fn complete_common_prefix(&self) -> bool {
+        true
+    }
+
     fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
-        self.metadata.iter()
-            .filter(|&&(ref command, ref help)|
-                    command.to_lowercase().contains(&input) ||
-                    help.to_lowercase().contains(&input))
-            .map(|&(ref col1, ref col2)| CompletionResult::new(&[col1, col2]))
-            .collect()
+        let rows = self.metadata.iter()
+            .map(|(command, help)| vec![command.as_str(), help.as_str()]);
+        fuzzy_completions(input, rows)
+    }
+}
+
+/// A completer listing errors with their location, completing their number.
+pub struct ErrorCompleter {
+    errors: Vec<(String, String)>,
+}
+
+impl ErrorCompleter {
+    /// Create a new error completer from the location and the message of the errors.
+    pub fn new(errors: Vec<(String, String)>) -> Self {
+        ErrorCompleter {
+            errors,
+        }
+    }
+}
+
+impl Completer for ErrorCompleter {
+    fn columns(&self) -> Vec<Column> {
+        vec![Column::AllVisible, Column::AllVisible, Expand]
+    }
+
+    fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
+        let numbers: Vec<_> = (1..=self.errors.len()).map(|number| number.to_string()).collect();
+        let rows = numbers.iter().zip(&self.errors)
+            .map(|(number, (location, message))| vec![number.as_str(), location.as_str(), message.as_str()]);
+        fuzzy_completions(input.trim(), rows)
+    }
+}
+
+/// A key binding completer.
+pub struct MappingCompleter {
+    command: Option<String>,
+    mappings: Vec<(String, String)>,
+}
+
+impl MappingCompleter {
+    /// Create a new key binding completer from the keys and the action of the bindings.
+    pub fn new(mut mappings: Vec<(String, String)>) -> Self {
+        mappings.sort();
+        MappingCompleter {
+            command: None,
+            mappings,
+        }
+    }
+
+    /// Prefix the completed keys by the command, for when this completer is used for the whole
+    /// input instead of for an argument.
+    pub fn command(mut self, command: &str) -> Self {
+        self.command = Some(command.to_string());
+        self
+    }
+}
+
+impl Completer for MappingCompleter {
+    fn columns(&self) -> Vec<Column> {
+        vec![Column::AllVisible, Expand]
+    }
+
+    fn complete_result(&self, value: &str) -> String {
+        match self.command {
+            Some(ref command) => format!("{} {}", command, value),
+            None => value.to_string(),
+        }
+    }
+
+    fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
+        let rows = self.mappings.iter()
+            .map(|(keys, action)| vec![keys.as_str(), action.as_str()]);
+        fuzzy_completions(input.trim(), rows)
+    }
+}
+
+/// A completer listing messages with their time and level, the most recent first, completing
+/// their number.
+pub struct MessageCompleter {
+    messages: Vec<(String, String, String)>,
+}
+
+impl MessageCompleter {
+    /// Create a new message completer from the time, the level and the text of the messages.
+    pub fn new(messages: Vec<(String, String, String)>) -> Self {
+        MessageCompleter {
+            messages,
+        }
+    }
+}
+
+impl Completer for MessageCompleter {
+    fn columns(&self) -> Vec<Column> {
+        vec![Column::AllVisible, Column::AllVisible, Column::AllVisible, Expand]
+    }
+
+    fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
+        let numbers: Vec<_> = (1..=self.messages.len()).map(|number| number.to_string()).collect();
+        let rows = numbers.iter().zip(&self.messages)
+            .map(|(number, (time, level, text))| vec![number.as_str(), time.as_str(), level.as_str(), text.as_str()]);
+        fuzzy_completions(input.trim(), rows)
     }
 }
 
@@ -86,11 +220,141 @@ impl Completer for NoCompleter {
     }
 }
 
+/// A filesystem path completer.
+pub struct PathCompleter {
+    command: Option<String>,
+    directory_foreground: String,
+    extensions: Vec<String>,
+    show_hidden: bool,
+}
+
+impl PathCompleter {
+    #[allow(unknown_lints, new_without_default_derive)]
+    /// Create a new path completer.
+    /// It completes the paths of files and directories, the directories ending with a slash.
+    pub fn new() -> Self {
+        PathCompleter {
+            command: None,
+            directory_foreground: DIRECTORY_FOREGROUND.to_string(),
+            extensions: vec![],
+            show_hidden: false,
+        }
+    }
+
+    /// Prefix the completed path by the command, for when this completer is used for the whole
+    /// input instead of for an argument.
+    pub fn command(mut self, command: &str) -> Self {
+        self.command = Some(command.to_string());
+        self
+    }
+
+    /// Set the foreground color of the directories.
+    pub fn directory_foreground(mut self, foreground: &str) -> Self {
+        self.directory_foreground = foreground.to_string();
+        self
+    }
+
+    /// Only show the files having one of these extensions.
+    /// The directories are always shown.
+    pub fn extensions(mut self, extensions: &[&str]) -> Self {
+        self.extensions = extensions.iter().map(|extension| extension.to_string()).collect();
+        self
+    }
+
+    /// Show the hidden files, even when the input does not start with a dot.
+    pub fn show_hidden(mut self, show_hidden: bool) -> Self {
+        self.show_hidden = show_hidden;
+        self
+    }
+
+    /// Check if the file should be shown according to its extension.
+    fn has_extension(&self, path: &Path) -> bool {
+        if self.extensions.is_empty() {
+            return true;
+        }
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| self.extensions.iter().any(|wanted| wanted == extension))
+            .unwrap_or(false)
+    }
+}
+
+impl Completer for PathCompleter {
+    fn columns(&self) -> Vec<Column> {
+        vec![Expand]
+    }
+
+    fn complete_common_prefix(&self) -> bool {
+        true
+    }
+
+    fn complete_result(&self, value: &str) -> String {
+        match self.command {
+            Some(ref command) => format!("{} {}", command, value),
+            None => value.to_string(),
+        }
+    }
+
+    fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
+        // The ~ is kept in the completions, so that they extend the input.
+        let input = match input.trim() {
+            "~" => "~/",
+            input => input,
+        };
+        let (directory, file_prefix) =
+            match input.rfind('/') {
+                Some(index) => input.split_at(index + 1),
+                None => ("", input),
+            };
+        let entries =
+            match read_dir(if directory.is_empty() { ".".to_string() } else { expand_home(directory) }) {
+                Ok(entries) => entries,
+                Err(_) => return vec![],
+            };
+        let show_hidden = self.show_hidden || file_prefix.starts_with('.');
+        let mut paths: Vec<_> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                if !name.starts_with(file_prefix) || (!show_hidden && name.starts_with('.')) {
+                    return None;
+                }
+                let path = entry.path();
+                let is_dir = path.is_dir();
+                if !is_dir && !self.has_extension(&path) {
+                    return None;
+                }
+                Some((!is_dir, name))
+            })
+            .collect();
+        // Show the directories first.
+        paths.sort();
+        paths.into_iter()
+            .map(|(is_file, name)| {
+                if is_file {
+                    CompletionResult::new(&[&format!("{}{}", directory, name)])
+                }
+                else {
+                    let cell = CompletionCell::new(&format!("{}{}/", directory, name))
+                        .foreground(&self.directory_foreground);
+                    CompletionResult::from_cells(&[&cell])
+                }
+            })
+            .collect()
+    }
+}
+
 /// A setting completer.
+/// It can show the type of the settings and, when they are known, their current and default
+/// values.
 pub struct SettingCompleter<T> {
+    current_values: HashMap<String, String>,
+    default_values: HashMap<String, String>,
     selected_name: Option<String>,
     setting_names: Vec<(String, String)>,
+    setting_types: HashMap<String, String>,
     setting_values: HashMap<String, Vec<String>>,
+    unknown_values: HashSet<String>,
     _phantom: PhantomData<T>,
 }
 
@@ -105,15 +369,102 @@ impl<T: EnumMetaData + SettingCompletion> SettingCompleter<T> {
                 .collect();
         data.sort();
         SettingCompleter {
+            current_values: HashMap::new(),
+            default_values: HashMap::new(),
             selected_name: None,
             setting_names: data,
+            setting_types: HashMap::new(),
             setting_values: T::get_value_completions(),
+            unknown_values: HashSet::new(),
             _phantom: PhantomData,
         }
     }
+
+    /// Show the current values of the settings, by name.
+    pub fn current_values(mut self, values: HashMap<String, String>) -> Self {
+        self.current_values = values;
+        self
+    }
+
+    /// Set the default values of the settings, by name, to show whether the current values were
+    /// changed.
+    pub fn default_values(mut self, values: HashMap<String, String>) -> Self {
+        self.default_values = values;
+        self
+    }
+
+    /// Set the settings whose current values are unknown, for instance because the application
+    /// changed them, so that neither their previous nor their default values are shown.
+    pub fn unknown_values(mut self, names: HashSet<String>) -> Self {
+        self.unknown_values = names;
+        self
+    }
+}
+
+impl<T: EnumMetaData + SettingCompletion + Settings> SettingCompleter<T> {
+    /// Show the types of the settings and complete true and false for the bool settings.
+    pub fn types(mut self) -> Self {
+        self.setting_types = self.setting_names.iter()
+            .filter_map(|(name, _)| Some((name.clone(), setting_type::<T>(name)?)))
+            .collect();
+        self
+    }
+}
+
+impl<T> SettingCompleter<T> {
+    /// Get the cell showing the value of the setting, its type and whether it was changed from its
+    /// default value.
+    fn value_cell(&self, name: &str) -> CompletionCell {
+        let typ = self.setting_types.get(name);
+        let details = |state: &str| match typ {
+            Some(typ) => format!("{}, {}", typ, state),
+            None => state.to_string(),
+        };
+        if self.unknown_values.contains(name) {
+            return CompletionCell::new(&format!("unknown ({})", details("modified")))
+                .bold();
+        }
+        let default_value = self.default_values.get(name);
+        match self.current_values.get(name).or(default_value) {
+            Some(value) => {
+                if default_value == Some(value) {
+                    CompletionCell::new(&format!("{} ({})", value, details("default")))
+                        .dimmed()
+                }
+                else {
+                    CompletionCell::new(&format!("{} ({})", value, details("modified")))
+                        .bold()
+                }
+            },
+            None => CompletionCell::new(&typ.map(|typ| format!("({})", typ)).unwrap_or_default()).dimmed(),
+        }
+    }
+
+    /// Get the current value of the setting, if it is known.
+    fn current_value(&self, name: &str) -> Option<&String> {
+        if self.unknown_values.contains(name) {
+            return None;
+        }
+        self.current_values.get(name).or_else(|| self.default_values.get(name))
+    }
+
+    /// Get the values to complete for the setting: the ones from the setting or true and false
+    /// for booleans.
+    fn values(&self, name: &str) -> Option<Vec<String>> {
+        match self.setting_values.get(name) {
+            Some(values) if !values.is_empty() => Some(values.clone()),
+            _ if self.setting_types.get(name).map(String::as_str) == Some("bool") =>
+                Some(vec!["true".to_string(), "false".to_string()]),
+            values => values.cloned(),
+        }
+    }
 }
 
 impl<T> Completer for SettingCompleter<T> {
+    fn columns(&self) -> Vec<Column> {
+        vec![Expand, Expand, Column::AllVisible]
+    }
+
     fn complete_result(&self, value: &str) -> String {
         if let Some(ref name) = self.selected_name {
             format!("set {} = {}", name, value)
@@ -127,14 +478,26 @@ impl<T> Completer for SettingCompleter<T> {
         if input.contains("= ") {
             let mut iter = input.split_whitespace();
             if let Some(name) = iter.next() {
-                if let Some(values) = self.setting_values.get(name) {
+                if let Some(values) = self.values(name) {
                     iter.next(); // Skip the equal token.
                     let input_value = iter.next().unwrap_or_default();
                     self.selected_name = Some(name.to_string());
-                    return values.iter()
-                        .filter(|value| value.contains(input_value))
-                        .map(|value| CompletionResult::new(&[value, &String::new()]))
-                        .collect();
+                    let current_value = self.current_value(name);
+                    // Only the values are matched, not their state.
+                    let rows = values.iter().map(|value| vec![value.as_str()]);
+                    let mut results = fuzzy_completions(input_value, rows);
+                    for result in &mut results {
+                        let state =
+                            if current_value == Some(&result.columns[0].value) {
+                                "current value"
+                            }
+                            else {
+                                ""
+                            };
+                        result.columns.push(CompletionCell::new(state));
+                        result.columns.push(CompletionCell::new(""));
+                    }
+                    return results;
                 }
             }
             vec![]
@@ -142,12 +505,13 @@ impl<T> Completer for SettingCompleter<T> {
         else {
             let input = input.trim();
             self.selected_name = None;
-            self.setting_names.iter()
-                .filter(|&&(ref setting, ref help)|
-                        setting.to_lowercase().contains(input) ||
-                        help.to_lowercase().contains(input))
-                .map(|&(ref col1, ref col2)| CompletionResult::new(&[col1, col2]))
-                .collect()
+            let rows = self.setting_names.iter()
+                .map(|(setting, help)| vec![setting.as_str(), help.as_str(), ""]);
+            let mut results = fuzzy_completions(input, rows);
+            for result in &mut results {
+                result.columns[2] = self.value_cell(&result.columns[0].value);
+            }
+            results
         }
     }
 }
@@ -19,70 +19,178 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
+use std::borrow::Cow;
 use std::cmp::max;
 
+use gdk::{EventButton, EventScroll, EventType, ScrollDirection};
 use glib::{Cast, Object};
 use glib::object::IsA;
 use gtk;
 use gtk::{
     traits::{
+        BoxExt,
         CellRendererTextExt,
-        GtkListStoreExt,
+        LabelExt,
+        OrientableExt,
         ScrolledWindowExt,
         TreeModelExt,
         TreeSelectionExt,
         TreeViewColumnExt,
+        CellRendererExt,
         TreeViewExt,
         WidgetExt,
     },
     Align,
+    CellRendererPixbuf,
     CellRendererText,
+    Inhibit,
     ListStore,
+    PackType,
     TreeIter,
     TreeModel,
     TreeViewColumn,
+    TreeViewColumnSizing,
 };
+use gtk::Orientation::Vertical;
 use gtk::PolicyType::{Automatic, Never};
 use pango::EllipsizeMode;
-use relm::{Relm, Widget};
+use relm::{Channel, Relm, Widget};
 use relm_derive::widget;
 
 use app::COMMAND_MODE;
-use completion::Completers;
+use completion::{ArgumentCompleters, CompletionBatch, Completers};
 use completion::Column::{self, Expand};
 use self::Msg::*;
-use super::{Completer, Completion, DEFAULT_COMPLETER_IDENT, NO_COMPLETER_IDENT};
+use super::{Completer, Completion, ModelColumn, DEFAULT_COMPLETER_IDENT, NO_COMPLETER_IDENT, is_header};
 
 const COMPLETION_VIEW_MAX_HEIGHT: i32 = 300;
 
+/// Where the completion view is shown in the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Placement {
+    /// Above the status bar, taking the whole width of the window.
+    AboveStatusBar,
+    /// In the center of the window, like a command palette, with the specified width in pixels.
+    Centered(i32),
+    /// Taking the whole height of the window.
+    FullHeight,
+}
+
+/// The maximum height of the completion view.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Height {
+    /// The height in pixels.
+    Pixels(i32),
+    /// The height as a number of rows.
+    Rows(i32),
+}
+
+/// The configuration of the completion view.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ViewConfig {
+    headers: bool,
+    max_height: Height,
+    placement: Placement,
+}
+
+impl ViewConfig {
+    /// Create the default configuration: the completion view is shown above the status bar,
+    /// without headers.
+    pub fn new() -> Self {
+        ViewConfig {
+            headers: false,
+            max_height: Height::Pixels(COMPLETION_VIEW_MAX_HEIGHT),
+            placement: Placement::AboveStatusBar,
+        }
+    }
+
+    /// Show the column titles of the completers as headers.
+    pub fn headers(mut self, headers: bool) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Set the maximum height of the completion view.
+    /// It is ignored when the completion view takes the whole height of the window.
+    pub fn max_height(mut self, max_height: Height) -> Self {
+        self.max_height = max_height;
+        self
+    }
+
+    /// Set where the completion view is shown.
+    pub fn placement(mut self, placement: Placement) -> Self {
+        self.placement = placement;
+        self
+    }
+}
+
+impl Default for ViewConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[allow(missing_docs)]
 pub struct Model {
+    _batch_channel: Channel<CompletionBatch>,
+    columns_completer_ident: String,
     completion: Completion,
+    config: ViewConfig,
     original_input: String,
     relm: Relm<CompletionView>,
+    scroll_delta: f64,
     visible: bool,
 }
 
+pub type Cursor = usize;
 pub type Mode = String;
 pub type Text = String;
 
 #[derive(Msg)]
 pub enum Msg {
+    AcceptCompletion(String),
+    Action(String),
+    ActionRows(String, Vec<Vec<String>>),
+    Activate,
+    AddArgumentCompleters(ArgumentCompleters),
+    AddBatch(CompletionBatch),
+    AddCompleter(Cow<'static, str>, Box<dyn Completer>),
     AddCompleters(Completers),
+    ButtonPress(EventButton),
+    CompletePrefix,
     Completer(String),
     CompletionChange(String),
     DeleteCurrentCompletionItem,
+    FirstCompletion(Option<String>),
+    InvertMarks,
+    MarkAll,
+    PrefixCompletion(String),
+    ResizeColumns(i32),
+    Scroll(EventScroll),
     SelectNext,
     SelectPrevious,
+    SetConfig(ViewConfig),
     SetOriginalInput(String),
     ShowCompletion,
-    UpdateCompletions(Mode, Text, bool),
+    ToggleMark,
+    UpdateCompletions(Mode, Text, Cursor, bool),
     Visible(bool),
 }
 
 /// A widget to show completions for the command entry.
 #[widget]
 impl Widget for CompletionView {
+    /// Add the results received from an async completer.
+    fn add_batch(&mut self, batch: CompletionBatch) {
+        if self.model.completion.add_batch(batch) {
+            if let Some(model) = self.model.completion.model() {
+                self.adjust_policy(model);
+            }
+            self.widgets.loading_label.set_visible(self.model.completion.is_loading());
+            self.emit_first_completion();
+        }
+    }
+
     fn add_completers(&mut self, completers: Completers) {
         for (ident, completer) in completers {
             self.model.completion.add_completer(ident, completer);
@@ -91,19 +199,41 @@ impl Widget for CompletionView {
 
     fn init_view(&mut self) {
         self.add_columns(2);
+        // The category headers cannot be selected.
+        self.widgets.tree_view.selection().set_select_function(Some(Box::new(|_, model, path, _| {
+            model.iter(path)
+                .map(|iter| !is_header(model, &iter))
+                .unwrap_or(true)
+        })));
     }
 
     fn model(relm: &Relm<Self>, completers: Completers) -> Model {
         let mut completion = Completion::new();
         completion.set_completers(completers);
+        let stream = relm.stream().clone();
+        let (batch_channel, batch_sender) = Channel::new(move |batch| stream.emit(AddBatch(batch)));
+        completion.set_batch_sender(batch_sender);
         Model {
+            _batch_channel: batch_channel,
+            columns_completer_ident: String::new(),
             completion,
+            config: ViewConfig::new(),
             original_input: String::new(),
             relm: relm.clone(),
+            scroll_delta: 0.0,
             visible: false,
         }
     }
 
+    /// Show or hide the completion view.
+    /// The marks are cleared when it is hidden, so that they do not apply to the next input.
+    fn set_visible(&mut self, visible: bool) {
+        if !visible {
+            self.model.completion.clear_marks();
+        }
+        self.model.visible = visible;
+    }
+
     /// Show the completion view.
     fn show_completion(&mut self) {
         self.unselect();
@@ -113,58 +243,141 @@ impl Widget for CompletionView {
 
     fn update(&mut self, msg: Msg) {
         match msg {
+            AcceptCompletion(input) => {
+                let selection = self.widgets.tree_view.selection();
+                // The input might have been edited after selecting a completion.
+                let completed = self.model.completion.complete_result(&selection);
+                if completed.as_ref().map(|completed| completed.trim()) == Some(input.trim()) {
+                    self.model.completion.accept_selection(&selection);
+                }
+            },
+            Action(action) => self.action(action),
+            // NOTE: to be listened by the user.
+            ActionRows(_, _) => (),
+            // NOTE: to be listened by the user.
+            Activate => (),
+            AddArgumentCompleters(argument_completers) =>
+                self.model.completion.add_argument_completers(argument_completers),
+            AddBatch(batch) => self.add_batch(batch),
+            AddCompleter(ident, completer) => self.model.completion.add_completer(ident, completer),
             AddCompleters(completers) => self.add_completers(completers),
-            Completer(completer) => self.set_completer(&completer, ""),
+            ButtonPress(event) => self.button_press(&event),
+            CompletePrefix => self.complete_prefix(),
+            Completer(completer) => {
+                self.model.completion.clear_argument();
+                self.set_completer(&completer, "");
+            },
             // NOTE: to be listened by the user.
             CompletionChange(_) => (),
             DeleteCurrentCompletionItem => self.delete_current_completion_item(),
+            // NOTE: to be listened by the user.
+            FirstCompletion(_) => (),
+            InvertMarks => self.model.completion.invert_marks(),
+            MarkAll => self.model.completion.mark_all(),
+            // NOTE: to be listened by the user.
+            PrefixCompletion(_) => (),
+            ResizeColumns(width) => self.resize_columns(width),
+            Scroll(event) => self.scroll_selection(&event),
             SelectNext => self.select_next(),
             SelectPrevious => self.select_previous(),
+            SetConfig(config) => self.set_config(config),
             SetOriginalInput(input) => self.set_original_input(&input),
             ShowCompletion => self.show_completion(),
-            UpdateCompletions(mode, text, is_normal_command) =>
-                self.update_completions(&mode, &text, is_normal_command),
-            Visible(visible) => self.model.visible = visible,
+            ToggleMark => self.toggle_mark(),
+            UpdateCompletions(mode, text, cursor, is_normal_command) =>
+                self.update_completions(&mode, &text, cursor, is_normal_command),
+            Visible(visible) => self.set_visible(visible),
         }
     }
 
     view! {
-        #[name="scrolled_window"]
-        gtk::ScrolledWindow {
-            max_content_height: COMPLETION_VIEW_MAX_HEIGHT,
-            propagate_natural_height: true,
+        gtk::Box {
+            orientation: Vertical,
             valign: Align::End,
             visible: self.model.visible,
-            #[name="tree_view"]
-            gtk::TreeView {
-                can_focus: false,
-                enable_search: false,
-                headers_visible: false,
-            }
+            #[name="scrolled_window"]
+            gtk::ScrolledWindow {
+                max_content_height: COMPLETION_VIEW_MAX_HEIGHT,
+                propagate_natural_height: true,
+                #[name="tree_view"]
+                gtk::TreeView {
+                    can_focus: false,
+                    enable_search: false,
+                    headers_visible: false,
+                    // NOTE: the events are handled here since the tree view cannot have the focus,
+                    // which stays in the command entry.
+                    button_press_event(_, event) => (ButtonPress(event.clone()), Inhibit(true)),
+                    scroll_event(_, event) => (Scroll(event.clone()), Inhibit(true)),
+                    size_allocate(_, allocation) => ResizeColumns(allocation.width()),
+                }
+            },
+            #[name="loading_label"]
+            gtk::Label {
+                halign: Align::Start,
+                no_show_all: true,
+                text: "Loading…",
+                child: {
+                    pack_type: PackType::End,
+                },
+            },
         }
     }
 }
 
 impl CompletionView {
+    /// Send the rows on which to execute the action: the marked rows or the selected row if none
+    /// are marked.
+    fn action(&self, action: String) {
+        let rows =
+            if self.model.completion.has_marks() {
+                self.model.completion.marked_rows()
+            }
+            else {
+                self.model.completion.selected_row(&self.widgets.tree_view.selection())
+                    .into_iter()
+                    .collect()
+            };
+        if !rows.is_empty() {
+            self.model.relm.stream().emit(ActionRows(action, rows));
+        }
+    }
+
     /// Add a column to the tree view.
-    fn add_column(&self, index: i32, foreground_index: i32, column: Column) {
+    /// It shows the icon and the text of the completer column `index`.
+    fn add_column(&self, index: usize, column_count: usize, column: Column) {
+        let model_index = |model_column: ModelColumn| model_column.index(index, column_count);
         let view_column = TreeViewColumn::new();
+        let icon_cell = CellRendererPixbuf::new();
+        view_column.pack_start(&icon_cell, false);
+        view_column.add_attribute(&icon_cell, "gicon", model_index(ModelColumn::Icon));
+        view_column.add_attribute(&icon_cell, "cell-background", model_index(ModelColumn::Background));
+
         let cell = CellRendererText::new();
-        if column == Expand {
-            cell.set_ellipsize(EllipsizeMode::End);
-            view_column.set_expand(true);
+        match column {
+            Column::AllVisible => (),
+            Expand => {
+                cell.set_ellipsize(EllipsizeMode::End);
+                view_column.set_expand(true);
+            },
+            Column::Ratio(_) => {
+                // The width is set when the tree view is resized.
+                cell.set_ellipsize(EllipsizeMode::End);
+                view_column.set_sizing(TreeViewColumnSizing::Fixed);
+            },
         }
         view_column.pack_start(&cell, true);
-        view_column.add_attribute(&cell, "text", index);
-        view_column.add_attribute(&cell, "foreground", foreground_index);
+        view_column.add_attribute(&cell, "markup", model_index(ModelColumn::Markup));
+        view_column.add_attribute(&cell, "foreground", model_index(ModelColumn::Foreground));
+        view_column.add_attribute(&cell, "attributes", model_index(ModelColumn::Attributes));
+        view_column.add_attribute(&cell, "cell-background", model_index(ModelColumn::Background));
         self.widgets.tree_view.append_column(&view_column);
     }
 
     /// Add the specified number of columns.
-    fn add_columns(&self, column_count: i32) {
+    fn add_columns(&self, column_count: usize) {
         self.remove_columns();
         for i in 0 .. column_count {
-            self.add_column(i, column_count + i, Expand);
+            self.add_column(i, column_count, Expand);
         }
     }
 
@@ -172,21 +385,60 @@ impl CompletionView {
     fn add_columns_from_completer(&self, completer: &dyn Completer) {
         self.remove_columns();
         let columns = completer.columns();
-        let column_count = columns.len() as i32;
         for (i, column) in columns.iter().enumerate() {
-            let i = i as i32;
-            self.add_column(i, column_count + i, *column);
+            self.add_column(i, columns.len(), *column);
         }
     }
 
-    /// Adjust the columns from the completer.
+    /// Adjust the columns and their headers from the completer.
     fn adjust_columns(&self, completer: &dyn Completer) {
         self.add_columns_from_completer(completer);
+        let titles = completer.column_titles();
+        for (column, title) in self.widgets.tree_view.columns().iter().zip(&titles) {
+            column.set_title(title);
+        }
+        self.widgets.tree_view.set_headers_visible(self.model.config.headers && !titles.is_empty());
+    }
+
+    /// Adjust the maximum height of the completion view.
+    fn adjust_height(&self) {
+        let scrolled_window = &self.widgets.scrolled_window;
+        if self.model.config.placement == Placement::FullHeight {
+            scrolled_window.set_max_content_height(-1);
+            scrolled_window.set_propagate_natural_height(false);
+            scrolled_window.set_vexpand(true);
+        }
+        else {
+            let height =
+                match self.model.config.max_height {
+                    Height::Pixels(height) => height,
+                    Height::Rows(rows) => rows * self.row_height(),
+                };
+            scrolled_window.set_vexpand(false);
+            scrolled_window.set_propagate_natural_height(true);
+            scrolled_window.set_max_content_height(height);
+        }
+    }
+
+    /// Adjust the alignment of the completion view to its placement.
+    fn adjust_placement(&self) {
+        let root = self.root();
+        let (halign, valign, width) =
+            match self.model.config.placement {
+                Placement::AboveStatusBar => (Align::Fill, Align::End, -1),
+                Placement::Centered(width) => (Align::Center, Align::Center, width),
+                Placement::FullHeight => (Align::Fill, Align::Fill, -1),
+            };
+        root.set_halign(halign);
+        root.set_valign(valign);
+        root.set_width_request(width);
     }
 
     /// Adjust the policy of the scrolled window to avoid having extra space around the tree view.
     fn adjust_policy<M: IsA<Object> + IsA<TreeModel>>(&self, model: &M) {
-        self.widgets.tree_view.set_model(Some(model));
+        if self.widgets.tree_view.model().as_ref() != Some(model.upcast_ref()) {
+            self.widgets.tree_view.set_model(Some(model));
+        }
         let policy =
             if model.iter_n_children(None) < 2 {
                 Never
@@ -197,6 +449,52 @@ impl CompletionView {
         self.widgets.scrolled_window.set_policy(Never, policy);
     }
 
+    /// Select the clicked result and activate it on double-click.
+    /// Clicking the row of the remaining results shows the next page.
+    fn button_press(&mut self, event: &EventButton) {
+        if event.button() != 1 {
+            return;
+        }
+        let (x, y) = event.position();
+        let path =
+            match self.widgets.tree_view.path_at_pos(x as i32, y as i32) {
+                Some((Some(path), _, _, _)) => path,
+                _ => return,
+            };
+        if let Some(model) = self.widgets.tree_view.model() {
+            if let Some(iter) = model.iter(&path) {
+                if is_header(&model, &iter) {
+                    let next_iter = iter;
+                    if !model.iter_next(&next_iter) {
+                        self.model.completion.load_more();
+                    }
+                    return;
+                }
+                match event.event_type() {
+                    EventType::ButtonPress => {
+                        self.widgets.tree_view.selection().select_iter(&iter);
+                        self.complete_result();
+                    },
+                    // The first click already completed the input with this result.
+                    EventType::DoubleButtonPress => self.model.relm.stream().emit(Activate),
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    /// Extend the input to the longest common prefix of the results when no result is selected.
+    /// Otherwise, or if it cannot be extended, select the next result.
+    fn complete_prefix(&mut self) {
+        if self.widgets.tree_view.selection().selected().is_none() {
+            if let Some(completion) = self.model.completion.common_prefix_completion() {
+                self.model.relm.stream().emit(PrefixCompletion(completion));
+                return;
+            }
+        }
+        self.select_next();
+    }
+
     /// Complete the result for the selection using the current completer.
     fn complete_result(&self) {
         let selection = self.widgets.tree_view.selection();
@@ -205,13 +503,20 @@ impl CompletionView {
         }
     }
 
-    /// Delete the current completion item.
-    fn delete_current_completion_item(&self) {
-        if let Some((model, iter)) = self.widgets.tree_view.selection().selected() {
-            if let Ok(model) = model.downcast::<ListStore>() {
-                self.select_next();
-                model.remove(&iter);
-                self.adjust_policy(&model);
+    /// Delete the marked items or the current completion item if none are marked.
+    fn delete_current_completion_item(&mut self) {
+        if self.model.completion.has_marks() {
+            self.unselect();
+            self.model.completion.delete_marked();
+            if let Some(model) = self.model.completion.model() {
+                self.adjust_policy(model);
+            }
+        }
+        else if let Some((_, iter)) = self.widgets.tree_view.selection().selected() {
+            self.select_next();
+            self.model.completion.delete_row(&iter);
+            if let Some(model) = self.model.completion.model() {
+                self.adjust_policy(model);
             }
         }
     }
@@ -221,6 +526,12 @@ impl CompletionView {
         self.widgets.scrolled_window.set_policy(Never, Never);
     }
 
+    /// Send the input completed with the first result, to be used as a suggestion.
+    fn emit_first_completion(&self) {
+        let completion = self.model.completion.first_completion();
+        self.model.relm.stream().emit(FirstCompletion(completion));
+    }
+
     /// Filter the completion view.
     fn filter(&mut self, command_entry_text: &str) {
         // Disable the scrollbars so that commands without completion does not
@@ -230,6 +541,8 @@ impl CompletionView {
         if let Some(model) = model {
             self.adjust_policy(&model);
         }
+        self.widgets.loading_label.set_visible(self.model.completion.is_loading());
+        self.emit_first_completion();
     }
 
     /// Remove all the columns.
@@ -246,6 +559,29 @@ impl CompletionView {
         }
     }
 
+    /// Select the next or previous result when scrolling.
+    fn scroll_selection(&mut self, event: &EventScroll) {
+        match event.direction() {
+            ScrollDirection::Down => self.select_next(),
+            ScrollDirection::Up => self.select_previous(),
+            ScrollDirection::Smooth => {
+                // Smooth scrolling sends small deltas: move the selection once per accumulated
+                // scroll step.
+                let (_, delta_y) = event.delta();
+                self.model.scroll_delta += delta_y;
+                while self.model.scroll_delta >= 1.0 {
+                    self.model.scroll_delta -= 1.0;
+                    self.select_next();
+                }
+                while self.model.scroll_delta <= -1.0 {
+                    self.model.scroll_delta += 1.0;
+                    self.select_previous();
+                }
+            },
+            _ => (),
+        }
+    }
+
     /// Scroll to the first row.
     fn scroll_to_first(&self) {
         if let Some(model) = self.widgets.tree_view.model() {
@@ -258,10 +594,22 @@ impl CompletionView {
     }
 
     /// Select the completer based on the currently typed command.
-    fn select_completer(&mut self, command_entry_text: &str, is_normal_command: bool) {
+    /// The argument completers of the command have priority over the command completer.
+    fn select_completer(&mut self, command_entry_text: &str, cursor: usize, is_normal_command: bool) {
         let text = command_entry_text.trim_start();
+        let argument_completer =
+            if is_normal_command {
+                self.model.completion.argument_completer(command_entry_text, cursor)
+            }
+            else {
+                self.model.completion.clear_argument();
+                None
+            };
         let completer =
-            if let Some(space_index) = text.find(' ') {
+            if let Some(argument_completer) = argument_completer {
+                argument_completer
+            }
+            else if let Some(space_index) = text.find(' ') {
                 &text[..space_index]
             }
             else if is_normal_command {
@@ -275,13 +623,20 @@ impl CompletionView {
 
     /// Select the next item.
     /// This loops with the value that started the completion.
-    fn select_next(&self) {
+    /// The next page of results is shown when reaching the last result.
+    fn select_next(&mut self) {
         if let Some(model) = self.widgets.tree_view.model() {
             let selection = self.widgets.tree_view.selection();
             if let Some((model, selected_iter)) = selection.selected() {
-                if model.iter_next(&selected_iter) {
-                    selection.select_iter(&selected_iter);
-                    self.scroll(&model, &selected_iter);
+                let mut next_iter = selected_iter;
+                let mut has_next = next_result(&model, &next_iter);
+                if !has_next && self.model.completion.load_more() {
+                    next_iter = selected_iter;
+                    has_next = next_result(&model, &next_iter);
+                }
+                if has_next {
+                    selection.select_iter(&next_iter);
+                    self.scroll(&model, &next_iter);
                 }
                 else {
                     self.unselect();
@@ -289,8 +644,10 @@ impl CompletionView {
                 }
             }
             else if let Some(iter) = model.iter_first() {
-                self.scroll(&model, &iter);
-                selection.select_iter(&iter);
+                if !is_header(&model, &iter) || next_result(&model, &iter) {
+                    self.scroll(&model, &iter);
+                    selection.select_iter(&iter);
+                }
             }
             self.complete_result();
         }
@@ -298,11 +655,11 @@ impl CompletionView {
 
     /// Select the previous item.
     /// This loops with the value that started the completion.
-    fn select_previous(&self) {
+    fn select_previous(&mut self) {
         if let Some(model) = self.widgets.tree_view.model() {
             let selection = self.widgets.tree_view.selection();
             if let Some((model, selected_iter)) = selection.selected() {
-                if model.iter_previous(&selected_iter) {
+                if previous_result(&model, &selected_iter) {
                     selection.select_iter(&selected_iter);
                     self.scroll(&model, &selected_iter);
                 }
@@ -312,22 +669,79 @@ impl CompletionView {
                 }
             }
             else if let Some(iter) = model.iter_nth_child(None, max(0, model.iter_n_children(None) - 1)) {
-                self.scroll(&model, &iter);
-                selection.select_iter(&iter);
+                if !is_header(&model, &iter) || previous_result(&model, &iter) {
+                    self.scroll(&model, &iter);
+                    selection.select_iter(&iter);
+                }
             }
             self.complete_result();
         }
     }
 
+    /// Set the width of the columns sharing the width by ratio.
+    fn resize_columns(&self, width: i32) {
+        let columns =
+            match self.model.completion.current_completer() {
+                Some(completer) => completer.columns(),
+                None => return,
+            };
+        let view_columns = self.widgets.tree_view.columns();
+        let mut total_ratio = 0;
+        let mut available_width = width;
+        for (column, view_column) in columns.iter().zip(&view_columns) {
+            match *column {
+                Column::Ratio(ratio) => total_ratio += ratio,
+                _ => available_width -= view_column.width(),
+            }
+        }
+        if total_ratio == 0 {
+            return;
+        }
+        let available_width = max(0, available_width) as u32;
+        for (column, view_column) in columns.iter().zip(&view_columns) {
+            if let Column::Ratio(ratio) = *column {
+                let width = (available_width * ratio / total_ratio) as i32;
+                // Avoid resizing in a loop.
+                if view_column.fixed_width() != width {
+                    view_column.set_fixed_width(width);
+                }
+            }
+        }
+    }
+
+    /// Get the height of a row of text.
+    fn row_height(&self) -> i32 {
+        let cell = CellRendererText::new();
+        let (_, height) = cell.preferred_height(&self.widgets.tree_view);
+        let separator = self.widgets.tree_view.style_get_property("vertical-separator")
+            .get::<i32>()
+            .unwrap_or(0);
+        height + separator
+    }
+
+    /// Set the placement and size of the completion view.
+    fn set_config(&mut self, config: ViewConfig) {
+        self.model.config = config;
+        self.adjust_placement();
+        self.adjust_height();
+        if let Some(completer) = self.model.completion.current_completer() {
+            self.adjust_columns(completer);
+        }
+    }
+
     /// Set the current command completer.
     fn set_completer(&mut self, completer: &str, command_entry_text: &str) {
         if self.model.completion.adjust_model(completer) {
             let model: Option<&ListStore> = None;
             self.widgets.tree_view.set_model(model);
         }
-        {
-            let completer = self.model.completion.current_completer().expect("completer should be set");
-            self.adjust_columns(completer);
+        // Only recreate the columns when the completer changes.
+        if self.model.columns_completer_ident != self.model.completion.current_completer_ident() {
+            {
+                let completer = self.model.completion.current_completer().expect("completer should be set");
+                self.adjust_columns(completer);
+            }
+            self.model.columns_completer_ident = self.model.completion.current_completer_ident().to_string();
         }
         self.filter(command_entry_text);
     }
@@ -337,6 +751,14 @@ impl CompletionView {
         self.model.original_input = input.to_string();
     }
 
+    /// Toggle the mark of the selected item and select the next one.
+    fn toggle_mark(&mut self) {
+        if let Some((_, iter)) = self.widgets.tree_view.selection().selected() {
+            self.model.completion.toggle_mark(&iter);
+            self.select_next();
+        }
+    }
+
     /// Unselect the item.
     fn unselect(&self) {
         let selection = self.widgets.tree_view.selection();
@@ -344,23 +766,46 @@ impl CompletionView {
     }
 
     /// Update the completions.
-    fn update_completions(&mut self, current_mode: &str, command_entry_text: &str, is_normal_command: bool) {
+    fn update_completions(&mut self, current_mode: &str, command_entry_text: &str, cursor: usize,
+        is_normal_command: bool)
+    {
         if current_mode == COMMAND_MODE {
             // In command mode, the completer can change when the user type.
             // For instance, after typing "set ", the completer switch to the settings
             // completer.
             // TODO: add command_entry_text in the model?
-            self.select_completer(command_entry_text, is_normal_command);
+            self.select_completer(command_entry_text, cursor, is_normal_command);
         }
         else {
             // Do not select another completer when in input mode.
             self.filter(command_entry_text);
         }
+        // The results were filtered above, which also starts the async completers, so they must
+        // not be filtered again.
         if self.model.completion.current_completer_ident() != NO_COMPLETER_IDENT {
-            self.filter(command_entry_text);
             // return Some(SetOriginalInput(text); // TODO
             self.set_original_input(command_entry_text);
         }
         self.unselect();
     }
 }
+
+/// Move the iterator to the next row which is not a category header.
+fn next_result(model: &TreeModel, iter: &TreeIter) -> bool {
+    while model.iter_next(iter) {
+        if !is_header(model, iter) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Move the iterator to the previous row which is not a category header.
+fn previous_result(model: &TreeModel, iter: &TreeIter) -> bool {
+    while model.iter_previous(iter) {
+        if !is_header(model, iter) {
+            return true;
+        }
+    }
+    false
+}
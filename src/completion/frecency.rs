@@ -0,0 +1,226 @@
+/*
+ * Copyright (c) 2021 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Ranking of the completion results by frecency: the entries which were accepted frequently and
+//! recently come first.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use completion::{AsyncCompleter, Completer, CompletionResult};
+use completion::Column;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// The weight of a use depending on how many days ago it happened.
+const RECENCY_WEIGHTS: [(u64, u64); 4] = [(4, 100), (14, 70), (31, 50), (90, 30)];
+const OLD_WEIGHT: u64 = 10;
+
+struct Entry {
+    count: u64,
+    last_used: u64,
+}
+
+/// The history of the accepted completion entries, saved in a file.
+/// The file contains one entry per line: the number of uses, the timestamp of the last use and the
+/// value, separated by tabs.
+pub struct Frecency {
+    entries: HashMap<String, Entry>,
+    path: PathBuf,
+}
+
+impl Frecency {
+    /// Load the history from the file, which is created on the first accepted entry if it does
+    /// not exist.
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut entries = HashMap::new();
+        match File::open(&path) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    let mut fields = line.splitn(3, '\t');
+                    let count = fields.next().and_then(|count| count.parse().ok());
+                    let last_used = fields.next().and_then(|last_used| last_used.parse().ok());
+                    if let (Some(count), Some(last_used), Some(value)) = (count, last_used, fields.next()) {
+                        entries.insert(value.to_string(), Entry {
+                            count,
+                            last_used,
+                        });
+                    }
+                }
+            },
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => (),
+            Err(error) => return Err(error),
+        }
+        Ok(Frecency {
+            entries,
+            path,
+        })
+    }
+
+    /// Record that the value was accepted and save the history.
+    /// The whole file is rewritten synchronously, on the GTK thread when used by
+    /// `FrecencyCompleter`, so the history should stay small.
+    pub fn record(&mut self, value: &str) -> io::Result<()> {
+        let now = now();
+        let entry = self.entries.entry(value.to_string())
+            .or_insert(Entry {
+                count: 0,
+                last_used: now,
+            });
+        entry.count += 1;
+        entry.last_used = now;
+        self.save()
+    }
+
+    /// Remove the value from the history and save it.
+    pub fn remove(&mut self, value: &str) -> io::Result<()> {
+        if self.entries.remove(value).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Save the history in its file, rewriting it entirely.
+    fn save(&self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = File::create(&self.path)?;
+        for (value, entry) in &self.entries {
+            // NOTE: a value containing a new line could not be read back.
+            if !value.contains('\n') {
+                writeln!(file, "{}\t{}\t{}", entry.count, entry.last_used, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the frecency score of the value, 0 if it was never accepted.
+    pub fn score(&self, value: &str) -> u64 {
+        match self.entries.get(value) {
+            Some(entry) => {
+                let days = now().saturating_sub(entry.last_used) / DAY;
+                let weight = RECENCY_WEIGHTS.iter()
+                    .find(|&&(max_days, _)| days < max_days)
+                    .map(|&(_, weight)| weight)
+                    .unwrap_or(OLD_WEIGHT);
+                entry.count * weight
+            },
+            None => 0,
+        }
+    }
+
+    /// Sort the results by decreasing frecency of the value in the text column.
+    /// The results with the same frecency keep their order.
+    pub fn sort(&self, results: &mut [CompletionResult], text_column: usize) {
+        if self.entries.is_empty() {
+            return;
+        }
+        results.sort_by_key(|result| {
+            let score = result.columns.get(text_column)
+                .map(|cell| self.score(&cell.value))
+                .unwrap_or(0);
+            Reverse(score)
+        });
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Adapter ranking the results of a completer by frecency.
+/// The results of an async completer are not ranked since they are sent directly to the view.
+pub struct FrecencyCompleter<C> {
+    completer: C,
+    frecency: Frecency,
+}
+
+impl<C: Completer> FrecencyCompleter<C> {
+    /// Rank the results of the completer with the history saved in the file at `path`.
+    pub fn new<P: AsRef<Path>>(completer: C, path: P) -> io::Result<Self> {
+        Ok(FrecencyCompleter {
+            completer,
+            frecency: Frecency::new(path)?,
+        })
+    }
+}
+
+impl<C: Completer> Completer for FrecencyCompleter<C> {
+    fn accept(&mut self, value: &str) {
+        if let Err(error) = self.frecency.record(value) {
+            error!("Cannot save the completion history: {}", error);
+        }
+        self.completer.accept(value);
+    }
+
+    fn async_completer(&self) -> Option<Arc<dyn AsyncCompleter>> {
+        self.completer.async_completer()
+    }
+
+    fn column_titles(&self) -> Vec<String> {
+        self.completer.column_titles()
+    }
+
+    fn columns(&self) -> Vec<Column> {
+        self.completer.columns()
+    }
+
+    fn complete_common_prefix(&self) -> bool {
+        self.completer.complete_common_prefix()
+    }
+
+    fn complete_result(&self, value: &str) -> String {
+        self.completer.complete_result(value)
+    }
+
+    fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
+        let mut results = self.completer.completions(input);
+        self.frecency.sort(&mut results, self.completer.text_column() as usize);
+        results
+    }
+
+    fn delete(&mut self, row: &[String]) {
+        if let Some(value) = row.get(self.completer.text_column() as usize) {
+            if let Err(error) = self.frecency.remove(value) {
+                error!("Cannot save the completion history: {}", error);
+            }
+        }
+        self.completer.delete(row);
+    }
+
+    fn have_command(&self) -> bool {
+        self.completer.have_command()
+    }
+
+    fn text_column(&self) -> i32 {
+        self.completer.text_column()
+    }
+}
@@ -0,0 +1,178 @@
+/*
+ * Copyright (c) 2021 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+//! Fuzzy matcher to filter and rank completion results.
+
+use std::cmp::{Reverse, min};
+
+use completion::{CompletionCell, CompletionResult};
+
+const CAMEL_CASE_BONUS: i32 = 6;
+const CONSECUTIVE_BONUS: i32 = 8;
+const MATCH_SCORE: i32 = 16;
+const MAX_LEADING_PENALTY: i32 = 10;
+const SECONDARY_COLUMN_PENALTY: i32 = 20;
+const SEPARATOR_BONUS: i32 = 7;
+const START_BONUS: i32 = 8;
+
+/// The result of a successful fuzzy match.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FuzzyMatch {
+    /// The indices of the matched characters in the candidate.
+    pub indices: Vec<usize>,
+    /// The score of the match: the higher, the better.
+    pub score: i32,
+}
+
+/// Check whether all the characters of the query appear in order in the candidate.
+/// The comparison is case-insensitive.
+/// Matches at the start of words and consecutive matches give a better score, while characters
+/// skipped between matches lower the score.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query.chars().map(to_lowercase).collect();
+    if query.is_empty() {
+        return Some(FuzzyMatch {
+            indices: vec![],
+            score: 0,
+        });
+    }
+    let chars: Vec<char> = candidate.chars().collect();
+    let query_len = query.len();
+    let len = chars.len();
+    if query_len > len {
+        return None;
+    }
+
+    // scores[i * len + j] is the best score when the query is matched up to its character i and
+    // this character is matched at the index j of the candidate.
+    let mut scores: Vec<Option<i32>> = vec![None; query_len * len];
+    let mut previous = vec![0; query_len * len];
+    for i in 0..query_len {
+        // The best score plus index of the previous query character, for the matches not directly
+        // before the current index.
+        let mut best_gap: Option<(i32, usize)> = None;
+        for j in i..len {
+            if i > 0 && j >= 2 {
+                let k = j - 2;
+                if let Some(score) = scores[(i - 1) * len + k] {
+                    let value = score + k as i32;
+                    match best_gap {
+                        Some((best, _)) if best >= value => (),
+                        _ => best_gap = Some((value, k)),
+                    }
+                }
+            }
+            if to_lowercase(chars[j]) != query[i] {
+                continue;
+            }
+            let match_score = MATCH_SCORE + position_bonus(&chars, j);
+            if i == 0 {
+                scores[j] = Some(match_score - min(j as i32, MAX_LEADING_PENALTY));
+                continue;
+            }
+            let mut best = scores[(i - 1) * len + j - 1]
+                .map(|score| (score + CONSECUTIVE_BONUS, j - 1));
+            if let Some((value, k)) = best_gap {
+                // Penalize by the number of skipped characters.
+                let score = value - (j as i32 - 1);
+                match best {
+                    Some((best, _)) if best >= score => (),
+                    _ => best = Some((score, k)),
+                }
+            }
+            if let Some((score, k)) = best {
+                scores[i * len + j] = Some(score + match_score);
+                previous[i * len + j] = k;
+            }
+        }
+    }
+
+    let last_row = (query_len - 1) * len;
+    let (mut index, score) = (0..len)
+        .filter_map(|j| scores[last_row + j].map(|score| (j, score)))
+        .max_by_key(|&(j, score)| (score, Reverse(j)))?;
+    let mut indices = vec![index; query_len];
+    for i in (1..query_len).rev() {
+        index = previous[i * len + index];
+        indices[i - 1] = index;
+    }
+    Some(FuzzyMatch {
+        indices,
+        score,
+    })
+}
+
+/// Filter the rows matching the query and sort them from the best match to the worst.
+/// The query is matched against every column and a row gets the score of its best column, where
+/// the first column is favored.
+/// The matched characters are highlighted in the resulting cells.
+pub fn fuzzy_completions<'a, I>(query: &str, rows: I) -> Vec<CompletionResult>
+    where I: IntoIterator<Item=Vec<&'a str>>,
+{
+    let mut results: Vec<_> = rows.into_iter()
+        .filter_map(|columns| {
+            let matches: Vec<_> = columns.iter()
+                .map(|column| fuzzy_match(query, column))
+                .collect();
+            let score = matches.iter().enumerate()
+                .filter_map(|(index, fuzzy_match)| fuzzy_match.as_ref()
+                    .map(|fuzzy_match| fuzzy_match.score - index as i32 * SECONDARY_COLUMN_PENALTY))
+                .max()?;
+            let first_len = columns.first().map(|column| column.len()).unwrap_or_default();
+            let cells = columns.iter().zip(matches)
+                .map(|(column, fuzzy_match)| {
+                    let cell = CompletionCell::new(column);
+                    match fuzzy_match {
+                        Some(fuzzy_match) => cell.highlight(fuzzy_match.indices),
+                        None => cell,
+                    }
+                })
+                .collect();
+            Some((score, first_len, CompletionResult { category: None, columns: cells }))
+        })
+        .collect();
+    // NOTE: the sort is stable so that rows with the same score keep their original order.
+    results.sort_by_key(|&(score, len, _)| (Reverse(score), len));
+    results.into_iter()
+        .map(|(_, _, result)| result)
+        .collect()
+}
+
+/// Get the bonus for a character matched at the specified index.
+fn position_bonus(chars: &[char], index: usize) -> i32 {
+    if index == 0 {
+        return START_BONUS;
+    }
+    let previous = chars[index - 1];
+    if !previous.is_alphanumeric() {
+        SEPARATOR_BONUS
+    }
+    else if previous.is_lowercase() && chars[index].is_uppercase() {
+        CAMEL_CASE_BONUS
+    }
+    else {
+        0
+    }
+}
+
+fn to_lowercase(character: char) -> char {
+    character.to_lowercase().next().unwrap_or(character)
+}
@@ -21,13 +21,23 @@
 
 //! Trait and widget for input completion.
 
+mod async_completer;
 mod completers;
 #[allow(missing_docs)]
 pub mod completion_view;
+mod frecency;
+pub mod fuzzy;
 
+use std::borrow::Cow;
+use std::cmp::{max, min};
 use std::collections::HashMap;
+use std::mem;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-use glib::ToValue;
+use glib::{Cast, StaticType, ToValue};
+use glib::markup_escape_text;
 use glib::types::Type;
 use gtk::{
     prelude::GtkListStoreExtManual,
@@ -37,12 +47,28 @@ use gtk::{
         TreeSelectionExt,
     },
     ListStore,
+    TreeIter,
     TreeSelection,
 };
+use gtk::gio::{self, FileIcon, ThemedIcon};
+use pango::{AttrInt, AttrList, Weight};
+use relm::Sender;
 
 use self::Column::Expand;
-pub use self::completers::{CommandCompleter, NoCompleter, SettingCompleter};
-pub use self::completion_view::CompletionView;
+use self::async_completer::spawn_completions;
+pub use self::async_completer::{AsyncCompleter, CompletionBatch, CompletionSink, ThreadedCompleter};
+pub use self::completers::{
+    CommandCompleter,
+    ErrorCompleter,
+    MappingCompleter,
+    MessageCompleter,
+    NoCompleter,
+    PathCompleter,
+    SettingCompleter,
+};
+pub use self::completion_view::{CompletionView, Height, Placement, ViewConfig};
+pub use self::frecency::{Frecency, FrecencyCompleter};
+pub use self::fuzzy::{FuzzyMatch, fuzzy_completions, fuzzy_match};
 
 /// The identifier of the default completer.
 pub const DEFAULT_COMPLETER_IDENT: &str = "__mg_default";
@@ -53,6 +79,56 @@ pub const NO_COMPLETER_IDENT: &str = "__mg_no_completer";
 #[doc(hidden)]
 pub type Completers = HashMap<&'static str, Box<dyn Completer>>;
 
+/// The completer identifiers for each argument position of the commands, by command name.
+/// The options starting with -- before the arguments, like the context of a map command, are not
+/// counted in the positions and are not completed.
+pub type ArgumentCompleters = Vec<(Cow<'static, str>, Vec<&'static str>)>;
+
+const DEFAULT_PAGE_SIZE: usize = 200;
+const DIMMED_ALPHA: u16 = 0x8000;
+const MARKED_BACKGROUND: &str = "#75507b";
+
+/// The data stored in the model for each column of a completer.
+/// The model contains, in order, all the values, then all the foregrounds and so on.
+#[derive(Clone, Copy)]
+enum ModelColumn {
+    Value,
+    Foreground,
+    Attributes,
+    Markup,
+    Background,
+    Icon,
+}
+
+const MODEL_COLUMNS: [ModelColumn; 6] = [
+    ModelColumn::Value,
+    ModelColumn::Foreground,
+    ModelColumn::Attributes,
+    ModelColumn::Markup,
+    ModelColumn::Background,
+    ModelColumn::Icon,
+];
+
+/// The category and header flag are stored in the last two columns of the model.
+const EXTRA_MODEL_COLUMN_COUNT: i32 = 2;
+
+impl ModelColumn {
+    /// Get the index in the model of this data for the completer column `column`.
+    fn index(self, column: usize, column_count: usize) -> i32 {
+        (self as usize * column_count + column) as i32
+    }
+
+    /// Get the type of this data in the model.
+    fn typ(self) -> Type {
+        match self {
+            ModelColumn::Attributes => AttrList::static_type(),
+            ModelColumn::Icon => gio::Icon::static_type(),
+            ModelColumn::Background | ModelColumn::Foreground | ModelColumn::Markup | ModelColumn::Value =>
+                Type::STRING,
+        }
+    }
+}
+
 /// The type of a column.
 #[derive(Clone, Copy, PartialEq)]
 pub enum Column {
@@ -60,15 +136,104 @@ pub enum Column {
     AllVisible,
     /// Specifies that the column will expand, but can be truncated (ellipsized).
     Expand,
+    /// Specifies that the column takes a share of the width left by the other columns,
+    /// proportional to this ratio relative to the other `Ratio` columns.
+    /// It can be truncated (ellipsized).
+    Ratio(u32),
+}
+
+/// The argument of a command under the cursor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Argument {
+    /// The byte index of the end of the argument.
+    pub end: usize,
+    /// The position of the argument, 0 being the first argument after the command name.
+    pub index: usize,
+    /// The byte index of the start of the argument.
+    pub start: usize,
+}
+
+/// Get the argument under the cursor, which is a character position in the input.
+/// Return None if the cursor is on the command name.
+pub fn argument_at(input: &str, cursor: usize) -> Option<Argument> {
+    let cursor = input.char_indices()
+        .nth(cursor)
+        .map(|(index, _)| index)
+        .unwrap_or_else(|| input.len());
+    let mut words = vec![];
+    let mut word_start = None;
+    for (index, character) in input.char_indices() {
+        if character.is_whitespace() {
+            if let Some(start) = word_start.take() {
+                words.push((start, index));
+            }
+        }
+        else if word_start.is_none() {
+            word_start = Some(index);
+        }
+    }
+    if let Some(start) = word_start {
+        words.push((start, input.len()));
+    }
+
+    let &(_, command_end) = words.first()?;
+    if cursor <= command_end {
+        return None;
+    }
+    let mut index = 0;
+    for &(start, end) in &words[1..] {
+        if cursor < start {
+            break;
+        }
+        if cursor <= end {
+            return Some(Argument {
+                end,
+                index,
+                start,
+            });
+        }
+        index += 1;
+    }
+    // The cursor is on spaces: this is the start of a new argument.
+    Some(Argument {
+        end: cursor,
+        index,
+        start: cursor,
+    })
 }
 
 /// The trait completer is an interface to be satisfied by input completers.
 pub trait Completer {
+    /// Called when the user activates the input while the value from the text column of a result
+    /// is selected.
+    fn accept(&mut self, _value: &str) {
+    }
+
+    /// Return the async completer if the results are computed in a background thread.
+    /// In this case, `completions()` is not called.
+    fn async_completer(&self) -> Option<Arc<dyn AsyncCompleter>> {
+        None
+    }
+
+    /// The titles of the columns, shown as headers if the completion view is configured to show
+    /// them.
+    /// No headers are shown if it is empty.
+    fn column_titles(&self) -> Vec<String> {
+        vec![]
+    }
+
     /// The number of columns of the completer.
     fn columns(&self) -> Vec<Column> {
         vec![Expand, Expand]
     }
 
+    /// Return true to allow the complete-prefix command to extend the input to the longest common
+    /// prefix of the results, like in a shell.
+    /// In this case, `complete_result()` must accept a prefix of a value.
+    fn complete_common_prefix(&self) -> bool {
+        false
+    }
+
     /// The number of column.
     fn column_count(&self) -> usize {
         self.columns().len()
@@ -83,6 +248,20 @@ pub trait Completer {
     /// The results are on two columns, hence the 2-tuple.
     fn completions(&mut self, input: &str) -> Vec<CompletionResult>;
 
+    /// Called when the user deletes a result from the completion view, with the values of its
+    /// columns.
+    /// The completer should not return this result anymore, for instance by removing it from the
+    /// history.
+    fn delete(&mut self, _row: &[String]) {
+    }
+
+    /// Called when the user deletes the marked results from the completion view.
+    fn delete_rows(&mut self, rows: &[Vec<String>]) {
+        for row in rows {
+            self.delete(row);
+        }
+    }
+
     /// Return true if the completer is for an input containing a command.
     /// Return false otherwise.
     /// This will have the effect of removing the command when there's one it set to true.
@@ -98,8 +277,21 @@ pub trait Completer {
 
 /// Completion to use with a text Entry.
 pub struct Completion {
+    argument: Option<ArgumentInput>,
+    argument_completers: HashMap<String, Vec<&'static str>>,
+    batch_sender: Option<Sender<CompletionBatch>>,
     completer_ident: String,
-    completers: Completers,
+    completers: HashMap<Cow<'static, str>, Box<dyn Completer>>,
+    generation: Arc<AtomicUsize>,
+    key: String,
+    limit: usize,
+    loading: bool,
+    marked: Vec<Vec<String>>,
+    model: Option<ListStore>,
+    model_completer_ident: String,
+    page_size: usize,
+    pending: Vec<CompletionResult>,
+    rows: Vec<Row>,
 }
 
 impl Completion {
@@ -107,14 +299,47 @@ impl Completion {
     #[allow(unknown_lints, new_without_default_derive)]
     pub fn new() -> Self {
         Completion {
+            argument: None,
+            argument_completers: HashMap::new(),
+            batch_sender: None,
             completer_ident: String::new(),
             completers: HashMap::new(),
+            generation: Arc::new(AtomicUsize::new(0)),
+            key: String::new(),
+            limit: DEFAULT_PAGE_SIZE,
+            loading: false,
+            marked: vec![],
+            model: None,
+            model_completer_ident: String::new(),
+            page_size: DEFAULT_PAGE_SIZE,
+            pending: vec![],
+            rows: vec![],
+        }
+    }
+
+    /// Add a batch of results from an async completer to the model.
+    /// Return false if the batch is for an outdated query.
+    pub fn add_batch(&mut self, batch: CompletionBatch) -> bool {
+        if batch.generation != self.generation.load(Ordering::SeqCst) {
+            return false;
+        }
+        match batch.results {
+            Some(results) => self.append_results(group_by_category(results)),
+            None => self.loading = false,
+        }
+        true
+    }
+
+    /// Add the completers of the arguments of commands.
+    pub fn add_argument_completers(&mut self, argument_completers: ArgumentCompleters) {
+        for (command, completers) in argument_completers {
+            self.argument_completers.insert(command.into_owned(), completers);
         }
     }
 
     /// Add a new completer.
-    pub fn add_completer(&mut self, ident: &'static str, completer: Box<dyn Completer>) {
-        self.completers.insert(ident, completer);
+    pub fn add_completer<S: Into<Cow<'static, str>>>(&mut self, ident: S, completer: Box<dyn Completer>) {
+        self.completers.insert(ident.into(), completer);
     }
 
     /// Adjust the model by using the specified completer.
@@ -129,6 +354,85 @@ impl Completion {
         false
     }
 
+    /// Add the results after the rows already shown, up to the limit.
+    /// This is used for the batches of async completers and for the next pages.
+    fn append_results(&mut self, results: Vec<CompletionResult>) {
+        let model =
+            match self.model {
+                Some(ref model) => model.clone(),
+                None => return,
+            };
+        let mut rows = mem::take(&mut self.rows);
+        let mut old_rows = vec![];
+        if let Some(&Row::More(_)) = rows.last() {
+            old_rows.extend(rows.pop());
+        }
+        let unchanged_count = rows.len();
+        let rows = self.layout_rows(rows, results);
+        update_model(&model, unchanged_count, &old_rows, &rows[unchanged_count..]);
+        self.rows = rows;
+    }
+
+    /// Get the identifier of the completer of the argument under the cursor.
+    /// If there's one, the input is only completed around this argument.
+    pub fn argument_completer(&mut self, input: &str, cursor: usize) -> Option<&'static str> {
+        self.argument = None;
+        let argument = argument_at(input, cursor)?;
+        let mut words = input.split_whitespace();
+        let command = words.next()?;
+        let options = words.take_while(|word| word.starts_with("--")).count();
+        let index = argument.index.checked_sub(options)?;
+        let completer = *self.argument_completers.get(command)?.get(index)?;
+        self.argument = Some(ArgumentInput {
+            prefix: input[..argument.start].to_string(),
+            suffix: input[argument.end..].to_string(),
+            value: input[argument.start..argument.end].to_string(),
+        });
+        Some(completer)
+    }
+
+    /// Stop completing an argument: the completer will get the whole input.
+    pub fn clear_argument(&mut self) {
+        self.argument = None;
+    }
+
+    /// Tell the current completer that the selected result was accepted by the user.
+    pub fn accept_selection(&mut self, selection: &TreeSelection) {
+        if self.current_completer_ident() == NO_COMPLETER_IDENT {
+            return;
+        }
+        if let Some((model, iter)) = selection.selected() {
+            if let Some(completer) = self.current_completer_mut() {
+                let value: Option<String> = model.value(&iter, completer.text_column()).get().ok().flatten();
+                if let Some(value) = value {
+                    completer.accept(&value);
+                }
+            }
+        }
+    }
+
+    /// Get the input extended to the longest common prefix of the results.
+    /// Return None if the current completer does not allow it or if the input cannot be extended.
+    pub fn common_prefix_completion(&self) -> Option<String> {
+        if self.current_completer_ident() == NO_COMPLETER_IDENT {
+            return None;
+        }
+        let completer = self.current_completer()?;
+        if !completer.complete_common_prefix() {
+            return None;
+        }
+        let text_column = completer.text_column() as usize;
+        let values = self.rows.iter()
+            .filter_map(|row| match row {
+                Row::Result(result, _) => Some(result),
+                _ => None,
+            })
+            .chain(self.pending.iter())
+            .map(|result| result.columns.get(text_column).map(|cell| cell.value.as_str()).unwrap_or(""));
+        let prefix = common_prefix_extension(&self.key, values)?;
+        Some(self.complete_value(completer, prefix))
+    }
+
     /// Complete the result for the selection using the current completer.
     pub fn complete_result(&self, selection: &TreeSelection) -> Option<String> {
         let mut completion = None;
@@ -137,7 +441,7 @@ impl Completion {
                 if let Some(completer) = self.current_completer() {
                     let value: Option<String> = model.value(&iter, completer.text_column()).get().ok().flatten();
                     if let Some(value) = value {
-                        completion = Some(completer.complete_result(&value));
+                        completion = Some(self.complete_value(completer, &value));
                     }
                 }
             }
@@ -145,6 +449,15 @@ impl Completion {
         completion
     }
 
+    /// Get the input completed with the value, which replaces the argument being completed.
+    fn complete_value(&self, completer: &dyn Completer, value: &str) -> String {
+        let result = completer.complete_result(value);
+        match self.argument {
+            Some(ref argument) => format!("{}{}{}", argument.prefix, result, argument.suffix),
+            None => result,
+        }
+    }
+
     /// Get the current completer.
     pub fn current_completer(&self) -> Option<&dyn Completer> {
         self.completers.get(self.completer_ident.as_str())
@@ -162,52 +475,530 @@ impl Completion {
         &self.completer_ident
     }
 
+    /// Delete a row from the model and tell the current completer.
+    pub fn delete_row(&mut self, iter: &TreeIter) {
+        let row =
+            match self.row_index(iter).map(|index| &self.rows[index]) {
+                Some(Row::Result(result, _)) => result.values(),
+                _ => return,
+            };
+        if let Some(completer) = self.current_completer_mut() {
+            completer.delete(&row);
+        }
+        self.remove_row(iter);
+    }
+
+    /// Unmark all the rows, including the ones hidden by the filter.
+    pub fn clear_marks(&mut self) {
+        if self.marked.is_empty() {
+            return;
+        }
+        self.marked.clear();
+        let rows = self.rows.iter()
+            .map(|row| match row {
+                Row::Result(result, _) => Row::Result(result.clone(), false),
+                row => row.clone(),
+            })
+            .collect();
+        self.set_rows(rows);
+    }
+
+    /// Delete the visible marked rows and tell the current completer.
+    pub fn delete_marked(&mut self) {
+        let marked = self.marked_rows();
+        self.marked.retain(|values| !marked.contains(values));
+        if let Some(completer) = self.current_completer_mut() {
+            completer.delete_rows(&marked);
+        }
+        let rows = self.rows.iter()
+            .filter(|row| !matches!(**row, Row::Result(_, true)))
+            .cloned()
+            .collect();
+        self.set_rows(remove_orphan_headers(rows));
+    }
+
     /// Filter the rows from the input.
+    /// The model is reused while the completer stays the same: only the rows that changed are
+    /// updated.
+    /// For an async completer, the returned model is empty and the results are added as they are
+    /// received by `add_batch()`.
     pub fn filter(&mut self, input: &str) -> Option<ListStore> {
-        self.current_completer_mut()
-            .map(|completer| {
-                // Multiply by 2 because each column has a foreground column.
-                let columns = vec![Type::STRING; completer.column_count() * 2];
-                let model = ListStore::new(&columns);
-
-                // Remove the command to only send the value to the completer.
-                let key =
-                    if !completer.have_command() {
-                        input
-                    }
-                    else if let Some(index) = input.find(' ') {
-                        input[index + 1 ..].trim_start()
-                    }
-                    else {
-                        input
-                    };
-
-                for &CompletionResult { ref columns } in &completer.completions(key) {
-                    let row = model.insert(-1);
-                    let start_column = columns.len();
-                    for (index, cell) in columns.iter().enumerate() {
-                        model.set_value(&row, index as u32, &cell.value.to_value());
-                        if let Some(ref foreground) = cell.foreground {
-                            model.set_value(&row, (index + start_column) as u32, &foreground.to_value());
-                        }
+        // Any pending query of an async completer is now outdated.
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.loading = false;
+        self.limit = self.page_size;
+        self.pending.clear();
+        let (column_count, key) = {
+            let completer = self.current_completer()?;
+            // Remove the command to only send the value to the completer.
+            let key =
+                if let Some(ref argument) = self.argument {
+                    &argument.value
+                }
+                else if !completer.have_command() {
+                    input
+                }
+                else if let Some(index) = input.find(' ') {
+                    input[index + 1 ..].trim_start()
+                }
+                else {
+                    input
+                };
+            (completer.column_count(), key.to_string())
+        };
+        self.key = key.clone();
+        if self.model.is_none() || self.model_completer_ident != self.completer_ident {
+            let mut columns: Vec<_> = MODEL_COLUMNS.iter()
+                .flat_map(|model_column| vec![model_column.typ(); column_count])
+                .collect();
+            columns.push(Type::STRING);
+            columns.push(Type::BOOL);
+            self.model = Some(ListStore::new(&columns));
+            self.model_completer_ident = self.completer_ident.clone();
+            self.rows.clear();
+            self.marked.clear();
+        }
+
+        let generation = self.generation.clone();
+        let batch_sender = self.batch_sender.clone();
+        let mut results = vec![];
+        if let Some(completer) = self.current_completer_mut() {
+            match (completer.async_completer(), batch_sender) {
+                (Some(async_completer), Some(batch_sender)) => {
+                    spawn_completions(async_completer, key, generation, batch_sender);
+                    self.loading = true;
+                },
+                _ => results = completer.completions(&key),
+            }
+        }
+        let rows = self.layout_rows(vec![], group_by_category(results));
+        self.set_rows(rows);
+        self.model.clone()
+    }
+
+    /// Get the input completed with the first result.
+    pub fn first_completion(&self) -> Option<String> {
+        if self.current_completer_ident() == NO_COMPLETER_IDENT {
+            return None;
+        }
+        let completer = self.current_completer()?;
+        let text_column = completer.text_column() as usize;
+        self.rows.iter()
+            .filter_map(|row| match row {
+                Row::Result(result, _) => result.columns.get(text_column),
+                _ => None,
+            })
+            .next()
+            .map(|cell| self.complete_value(completer, &cell.value))
+    }
+
+    /// Check if there are results that are not shown yet.
+    pub fn has_more(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Check if some visible rows are marked.
+    pub fn has_marks(&self) -> bool {
+        self.rows.iter().any(|row| matches!(*row, Row::Result(_, true)))
+    }
+
+    /// Mark the visible rows which are not marked and unmark the others.
+    pub fn invert_marks(&mut self) {
+        let rows = self.rows.iter()
+            .map(|row| match row {
+                Row::Result(result, marked) => Row::Result(result.clone(), !marked),
+                row => row.clone(),
+            })
+            .collect();
+        self.set_rows_and_marks(rows);
+    }
+
+    /// Check if an async completer is still computing results.
+    pub fn is_loading(&self) -> bool {
+        self.loading
+    }
+
+    /// Check if the result is marked.
+    fn is_marked(&self, result: &CompletionResult) -> bool {
+        !self.marked.is_empty() && self.marked.contains(&result.values())
+    }
+
+    /// Add the rows for the results after `rows`, with a header before each new category.
+    /// The results over the limit are kept for the next page.
+    fn layout_rows(&mut self, mut rows: Vec<Row>, results: Vec<CompletionResult>) -> Vec<Row> {
+        let mut shown_count = rows.iter()
+            .filter(|row| matches!(**row, Row::Result(..)))
+            .count();
+        let mut category = rows.iter().rev()
+            .filter_map(|row| match *row {
+                Row::Result(ref result, _) => Some(result.category.clone()),
+                _ => None,
+            })
+            .next()
+            .unwrap_or(None);
+        for result in results {
+            if shown_count < self.limit && self.pending.is_empty() {
+                if let Some(ref result_category) = result.category {
+                    if category.as_ref() != Some(result_category) {
+                        rows.push(Row::Header(result_category.clone()));
                     }
                 }
-                model
+                category = result.category.clone();
+                let marked = self.is_marked(&result);
+                rows.push(Row::Result(result, marked));
+                shown_count += 1;
+            }
+            else {
+                self.pending.push(result);
+            }
+        }
+        if !self.pending.is_empty() {
+            rows.push(Row::More(self.pending.len()));
+        }
+        rows
+    }
+
+    /// Show the next page of results.
+    /// Return false if all the results were already shown.
+    pub fn load_more(&mut self) -> bool {
+        if self.pending.is_empty() {
+            return false;
+        }
+        self.limit += self.page_size;
+        let pending = mem::take(&mut self.pending);
+        self.append_results(pending);
+        true
+    }
+
+    /// Mark all the visible rows.
+    pub fn mark_all(&mut self) {
+        let rows = self.rows.iter()
+            .map(|row| match row {
+                Row::Result(result, _) => Row::Result(result.clone(), true),
+                row => row.clone(),
+            })
+            .collect();
+        self.set_rows_and_marks(rows);
+    }
+
+    /// Get the values of the columns of the visible marked rows, in the order they were marked.
+    /// The marked rows hidden by the filter are not included.
+    pub fn marked_rows(&self) -> Vec<Vec<String>> {
+        let visible: Vec<_> = self.rows.iter()
+            .filter_map(|row| match row {
+                Row::Result(result, true) => Some(result.values()),
+                _ => None,
             })
+            .collect();
+        self.marked.iter()
+            .filter(|values| visible.contains(values))
+            .cloned()
+            .collect()
+    }
+
+    /// Get the model of the current completer.
+    pub fn model(&self) -> Option<&ListStore> {
+        self.model.as_ref()
+    }
+
+    /// Remove a row from the model.
+    /// The header of its category is removed as well if it was the last result of this category.
+    pub fn remove_row(&mut self, iter: &TreeIter) {
+        if let Some(index) = self.row_index(iter) {
+            let mut rows = self.rows.clone();
+            rows.remove(index);
+            self.set_rows(remove_orphan_headers(rows));
+        }
+    }
+
+    /// Get the index in the rows of the model row.
+    fn row_index(&self, iter: &TreeIter) -> Option<usize> {
+        let model = self.model.as_ref()?;
+        let index = *model.path(iter)?.indices().first()? as usize;
+        if index < self.rows.len() {
+            Some(index)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Update the model to show the rows and update the marks from their state.
+    fn set_rows_and_marks(&mut self, rows: Vec<Row>) {
+        for row in &rows {
+            if let Row::Result(ref result, marked) = *row {
+                let values = result.values();
+                let index = self.marked.iter().position(|marked_values| *marked_values == values);
+                match (index, marked) {
+                    (None, true) => self.marked.push(values),
+                    (Some(index), false) => {
+                        self.marked.remove(index);
+                    },
+                    _ => (),
+                }
+            }
+        }
+        self.set_rows(rows);
+    }
+
+    /// Update the model to show the rows.
+    fn set_rows(&mut self, rows: Vec<Row>) {
+        if let Some(ref model) = self.model {
+            update_model(model, 0, &self.rows, &rows);
+        }
+        self.rows = rows;
+    }
+
+    /// Get the values of the columns of the selected row.
+    pub fn selected_row(&self, selection: &TreeSelection) -> Option<Vec<String>> {
+        let (_, iter) = selection.selected()?;
+        match self.rows.get(self.row_index(&iter)?) {
+            Some(Row::Result(result, _)) => Some(result.values()),
+            _ => None,
+        }
+    }
+
+    /// Set the sender used to receive the results of the async completers.
+    pub fn set_batch_sender(&mut self, sender: Sender<CompletionBatch>) {
+        self.batch_sender = Some(sender);
     }
 
     /// Set all the completers.
-    pub fn set_completers(&mut self, mut completers: Completers) {
-        completers.insert(NO_COMPLETER_IDENT, Box::new(NoCompleter::new()));
-        self.completers = completers;
+    pub fn set_completers(&mut self, completers: Completers) {
+        self.completers = completers.into_iter()
+            .map(|(ident, completer)| (ident.into(), completer))
+            .collect();
+        self.completers.insert(NO_COMPLETER_IDENT.into(), Box::new(NoCompleter::new()));
+    }
+
+    /// Toggle the mark of the row.
+    pub fn toggle_mark(&mut self, iter: &TreeIter) {
+        if let Some(index) = self.row_index(iter) {
+            let mut rows = self.rows.clone();
+            if let Row::Result(_, ref mut marked) = rows[index] {
+                *marked = !*marked;
+            }
+            self.set_rows_and_marks(rows);
+        }
+    }
+
+    /// Set the number of results shown at once.
+    /// The next results are shown when selecting past the last one.
+    pub fn set_page_size(&mut self, page_size: usize) {
+        self.page_size = max(page_size, 1);
+    }
+}
+
+/// The input around the argument being completed.
+struct ArgumentInput {
+    prefix: String,
+    suffix: String,
+    value: String,
+}
+
+/// A row of the model.
+#[derive(Clone, PartialEq)]
+enum Row {
+    /// The header of a category.
+    Header(String),
+    /// The row indicating the number of results not shown yet.
+    More(usize),
+    /// A result and whether it is marked.
+    Result(CompletionResult, bool),
+}
+
+impl Row {
+    /// Get the cells to show for this row.
+    fn cells(&self) -> Cow<'_, [CompletionCell]> {
+        match *self {
+            Row::Header(ref category) =>
+                Cow::Owned(vec![CompletionCell::new("").markup(&format!("<b>{}</b>", markup_escape_text(category)))]),
+            Row::More(count) =>
+                Cow::Owned(vec![CompletionCell::new("").dimmed().markup(&format!("<i>{} more…</i>", count))]),
+            Row::Result(ref result, false) => Cow::Borrowed(&result.columns),
+            Row::Result(ref result, true) =>
+                Cow::Owned(result.columns.iter()
+                    .map(|cell| cell.clone().background(MARKED_BACKGROUND))
+                    .collect()),
+        }
+    }
+}
+
+/// Get the longest common prefix of two strings.
+pub fn common_prefix<'a>(string1: &'a str, string2: &str) -> &'a str {
+    let end = string1.char_indices()
+        .zip(string2.chars())
+        .find(|&((_, char1), char2)| char1 != char2)
+        .map(|((index, _), _)| index)
+        .unwrap_or_else(|| min(string1.len(), string2.len()));
+    &string1[..end]
+}
+
+/// Get the longest common prefix of the values starting with the key, ignoring the case.
+/// The values not starting with the key, like fuzzy matches, are ignored.
+/// Return None if this prefix does not extend the key.
+pub fn common_prefix_extension<'a, I>(key: &str, values: I) -> Option<&'a str>
+    where I: IntoIterator<Item=&'a str>,
+{
+    let key = key.to_lowercase();
+    let mut prefix: Option<&str> = None;
+    for value in values {
+        if !value.to_lowercase().starts_with(&key) {
+            continue;
+        }
+        prefix = Some(match prefix {
+            Some(prefix) => common_prefix(prefix, value),
+            None => value,
+        });
+    }
+    let prefix = prefix?;
+    if prefix.chars().count() > key.chars().count() && prefix.to_lowercase().starts_with(&key) {
+        Some(prefix)
+    }
+    else {
+        None
+    }
+}
+
+/// Remove the headers of the categories which do not have any result anymore.
+fn remove_orphan_headers(rows: Vec<Row>) -> Vec<Row> {
+    let mut result: Vec<Row> = Vec::with_capacity(rows.len());
+    for row in rows {
+        if !matches!(row, Row::Result(..)) && matches!(result.last(), Some(&Row::Header(_))) {
+            result.pop();
+        }
+        result.push(row);
+    }
+    if let Some(&Row::Header(_)) = result.last() {
+        result.pop();
+    }
+    result
+}
+
+/// Group the results by category, in the order of the first result of each category.
+fn group_by_category(mut results: Vec<CompletionResult>) -> Vec<CompletionResult> {
+    let mut categories: Vec<Option<String>> = vec![];
+    for result in &results {
+        if !categories.contains(&result.category) {
+            categories.push(result.category.clone());
+        }
+    }
+    if categories.len() > 1 {
+        // NOTE: the sort is stable so that the results keep their order in a category.
+        results.sort_by_key(|result| categories.iter().position(|category| *category == result.category));
+    }
+    results
+}
+
+/// Get the index of the category column of the model.
+fn category_column<M: TreeModelExt>(model: &M) -> i32 {
+    model.n_columns() - EXTRA_MODEL_COLUMN_COUNT
+}
+
+/// Get the index of the header flag column of the model.
+fn header_column<M: TreeModelExt>(model: &M) -> i32 {
+    model.n_columns() - EXTRA_MODEL_COLUMN_COUNT + 1
+}
+
+/// Check if the row is a category header or the row indicating there are more results, which
+/// cannot be selected.
+fn is_header<M: TreeModelExt>(model: &M, iter: &TreeIter) -> bool {
+    model.value(iter, header_column(model)).get().unwrap_or(false)
+}
+
+/// Get the number of completer columns shown by the model.
+fn column_count(model: &ListStore) -> usize {
+    (model.n_columns() - EXTRA_MODEL_COLUMN_COUNT) as usize / MODEL_COLUMNS.len()
+}
+
+/// Update the rows of the model starting at `offset` from `old_rows` to `new_rows`.
+/// Only the rows that changed are written.
+fn update_model(model: &ListStore, offset: usize, old_rows: &[Row], new_rows: &[Row]) {
+    let iter = model.iter_nth_child(None, offset as i32);
+    for (index, row) in new_rows.iter().enumerate() {
+        match iter {
+            Some(ref iter) if index < old_rows.len() => {
+                if old_rows[index] != *row {
+                    write_row(model, iter, row);
+                }
+                model.iter_next(iter);
+            },
+            _ => write_row(model, &model.insert(-1), row),
+        }
+    }
+    if new_rows.len() < old_rows.len() {
+        if let Some(iter) = iter {
+            while model.remove(&iter) {
+            }
+        }
+    }
+}
+
+/// Write the values of a row in the model.
+fn write_row(model: &ListStore, iter: &TreeIter, row: &Row) {
+    let column_count = column_count(model);
+    let model_index = |model_column: ModelColumn, index| model_column.index(index, column_count) as u32;
+    let cells = row.cells();
+    for index in 0..column_count {
+        let cell = cells.get(index);
+        let value = cell.map(|cell| cell.value.clone());
+        let foreground = cell.and_then(|cell| cell.foreground.clone());
+        let attributes = cell.map(CompletionCell::attributes);
+        let markup = cell.map(CompletionCell::markup_value);
+        let background = cell.and_then(|cell| cell.background.clone());
+        let icon = cell.and_then(|cell| cell.icon.as_ref().map(CellIcon::to_gicon));
+        model.set_value(iter, model_index(ModelColumn::Value, index), &value.to_value());
+        model.set_value(iter, model_index(ModelColumn::Foreground, index), &foreground.to_value());
+        model.set_value(iter, model_index(ModelColumn::Attributes, index), &attributes.to_value());
+        model.set_value(iter, model_index(ModelColumn::Markup, index), &markup.to_value());
+        model.set_value(iter, model_index(ModelColumn::Background, index), &background.to_value());
+        model.set_value(iter, model_index(ModelColumn::Icon, index), &icon.to_value());
+    }
+    let (category, header) =
+        match *row {
+            Row::Header(ref category) => (Some(category.clone()), true),
+            Row::More(_) => (None, true),
+            Row::Result(ref result, _) => (result.category.clone(), false),
+        };
+    model.set_value(iter, category_column(model) as u32, &category.to_value());
+    model.set_value(iter, header_column(model) as u32, &header.to_value());
+}
+
+/// An icon shown at the start of a cell.
+#[derive(Clone, PartialEq)]
+pub enum CellIcon {
+    /// An image file.
+    File(PathBuf),
+    /// An icon name from the icon theme.
+    Name(String),
+}
+
+impl CellIcon {
+    fn to_gicon(&self) -> gio::Icon {
+        match *self {
+            CellIcon::File(ref path) => FileIcon::new(&gio::File::for_path(path)).upcast(),
+            CellIcon::Name(ref name) => ThemedIcon::new(name).upcast(),
+        }
     }
 }
 
 /// A completion cell is the value with attributes of one data in a row.
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct CompletionCell {
+    /// The background color of the cell or None if using the default color.
+    pub background: Option<String>,
+    /// Whether the text is shown in bold.
+    pub bold: bool,
+    /// Whether the text is shown dimmed.
+    pub dimmed: bool,
     /// The foreground color of the cell or None if using the default color.
     pub foreground: Option<String>,
+    /// The indices of the characters to highlight, like the ones matching the user input.
+    pub highlights: Vec<usize>,
+    /// The icon shown before the text.
+    pub icon: Option<CellIcon>,
+    /// The Pango markup to show instead of the value.
+    pub markup: Option<String>,
     /// The text value to show on the cell.
     pub value: String,
 }
@@ -216,16 +1007,91 @@ impl CompletionCell {
     /// Create a new cell.
     pub fn new(value: &str) -> Self {
         CompletionCell {
+            background: None,
+            bold: false,
+            dimmed: false,
             foreground: None,
+            highlights: vec![],
+            icon: None,
+            markup: None,
             value: value.to_string(),
         }
     }
 
+    /// Get the Pango attributes for the style of the cell and its highlighted characters.
+    fn attributes(&self) -> AttrList {
+        let attributes = AttrList::new();
+        if self.bold {
+            attributes.insert(AttrInt::new_weight(Weight::Bold));
+        }
+        if self.dimmed {
+            attributes.insert(AttrInt::new_foreground_alpha(DIMMED_ALPHA));
+        }
+        // NOTE: the highlights are for the value, so they are not shown with markup.
+        if self.markup.is_some() {
+            return attributes;
+        }
+        for (index, (byte_index, character)) in self.value.char_indices().enumerate() {
+            if self.highlights.contains(&index) {
+                let mut attribute = AttrInt::new_weight(Weight::Bold);
+                attribute.set_start_index(byte_index as u32);
+                attribute.set_end_index((byte_index + character.len_utf8()) as u32);
+                attributes.insert(attribute);
+            }
+        }
+        attributes
+    }
+
+    /// Set the background color of the cell.
+    pub fn background(mut self, background: &str) -> Self {
+        self.background = Some(background.to_string());
+        self
+    }
+
+    /// Show the text in bold.
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    /// Show the text dimmed.
+    pub fn dimmed(mut self) -> Self {
+        self.dimmed = true;
+        self
+    }
+
     /// Set the foreground color of the cell.
     pub fn foreground(mut self, foreground: &str) -> Self {
         self.foreground = Some(foreground.to_string());
         self
     }
+
+    /// Set the indices of the characters to highlight.
+    pub fn highlight(mut self, indices: Vec<usize>) -> Self {
+        self.highlights = indices;
+        self
+    }
+
+    /// Set the icon shown before the text.
+    pub fn icon(mut self, icon: CellIcon) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    /// Show this Pango markup instead of the value.
+    /// The value is still the text used to complete the input.
+    pub fn markup(mut self, markup: &str) -> Self {
+        self.markup = Some(markup.to_string());
+        self
+    }
+
+    /// Get the markup to show in the cell.
+    fn markup_value(&self) -> String {
+        match self.markup {
+            Some(ref markup) => markup.clone(),
+            None => markup_escape_text(&self.value).to_string(),
+        }
+    }
 }
 
 /// Trait to specify that a type can be converted to a `CompletionCell`.
@@ -253,7 +1119,10 @@ impl ToCell for String {
 }
 
 /// A result to show in the completion view.
+#[derive(Clone, PartialEq)]
 pub struct CompletionResult {
+    /// The category of the result, shown as a header above the results of this category.
+    pub category: Option<String>,
     /// The columns data.
     pub columns: Vec<CompletionCell>,
 }
@@ -263,6 +1132,7 @@ impl CompletionResult {
     pub fn new(cols: &[&str]) -> Self {
         let cols: Vec<_> = cols.iter().map(|col| CompletionCell::new(col)).collect();
         CompletionResult {
+            category: None,
             columns: cols,
         }
     }
@@ -270,7 +1140,21 @@ impl CompletionResult {
     /// Create a new completion result with foregrounds.
     pub fn from_cells(cols: &[&dyn ToCell]) -> Self {
         CompletionResult {
+            category: None,
             columns: cols.iter().map(|value| value.to_cell()).collect(),
         }
     }
+
+    /// Get the values of the columns.
+    fn values(&self) -> Vec<String> {
+        self.columns.iter()
+            .map(|cell| cell.value.clone())
+            .collect()
+    }
+
+    /// Set the category of the result.
+    pub fn category(mut self, category: &str) -> Self {
+        self.category = Some(category.to_string());
+        self
+    }
 }
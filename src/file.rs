@@ -19,11 +19,22 @@
  * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
  */
 
+use std::env;
 use std::fs::File;
 use std::path::Path;
 
 use mg_settings::errors::{Error, Result};
 
+/// Replace the ~ at the start of the path by the home directory.
+pub fn expand_home(path: &str) -> String {
+    if path == "~" || path.starts_with("~/") {
+        if let Some(home) = env::var_os("HOME") {
+            return format!("{}{}", home.to_string_lossy(), &path[1..]);
+        }
+    }
+    path.to_string()
+}
+
 pub fn open<P: AsRef<Path>>(path: P) -> Result<File> {
     match File::open(&path) {
         Ok(file) => Ok(file),
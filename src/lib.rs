@@ -78,13 +78,19 @@ pub struct Mode {
     pub show_count: bool,
 }
 
-pub use app::{Mg, parse_config};
+pub use app::{ConfigLayer, LoggedMessage, MessageLevel, Mg, parse_config, parse_config_reader};
 pub use app::Msg::{
     Alert,
     AppClose,
+    ArgumentCompleters,
     CloseWin,
     Completers,
+    CompletionAction,
+    CompletionActionItems,
     CompletionViewChange,
+    CompletionViewConfig,
+    ConfigLayers,
+    Contexts,
     CustomCommand,
     CustomDialog,
     DarkTheme,
@@ -92,16 +98,24 @@ pub use app::Msg::{
     Error,
     Info,
     Message,
+    MessageHistory,
+    MessageLogged,
     ModeChanged,
     Question,
     SetMode,
     SetSetting,
+    SetSettingValue,
     SettingChanged,
+    SettingDefaults,
     StatusBarVisible,
     Title,
+    UpdateDelay,
     Variables,
+    WatchConfig,
     Warning,
+    WriteConfig,
 };
+pub use app::context::Context;
 pub use app::dialog::{
     BlockingInputDialog,
     DialogBuilder,
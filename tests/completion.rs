@@ -0,0 +1,246 @@
+/*
+ * Copyright (c) 2021 Boucher, Antoni <bouanto@zoho.com>
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy of
+ * this software and associated documentation files (the "Software"), to deal in
+ * the Software without restriction, including without limitation the rights to
+ * use, copy, modify, merge, publish, distribute, sublicense, and/or sell copies of
+ * the Software, and to permit persons to whom the Software is furnished to do so,
+ * subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS
+ * FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR
+ * COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER
+ * IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN
+ * CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+ */
+
+extern crate gtk;
+extern crate mg;
+extern crate mg_settings;
+#[macro_use]
+extern crate mg_settings_macros;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::process;
+use std::rc::Rc;
+
+use gtk::traits::TreeModelExt;
+
+use mg::completion::{
+    Argument,
+    Completer,
+    Completers,
+    Completion,
+    CompletionResult,
+    Frecency,
+    FuzzyMatch,
+    MappingCompleter,
+    PathCompleter,
+    SettingCompleter,
+    argument_at,
+    common_prefix,
+    common_prefix_extension,
+    fuzzy_completions,
+    fuzzy_match,
+};
+
+#[test]
+fn test_argument_at() {
+    assert_eq!(argument_at("open", 2), None);
+    assert_eq!(argument_at("open", 4), None);
+    assert_eq!(argument_at("open ", 5), Some(Argument { end: 5, index: 0, start: 5 }));
+    assert_eq!(argument_at("open url", 8), Some(Argument { end: 8, index: 0, start: 5 }));
+    assert_eq!(argument_at("tabmove 1  2", 6), None);
+    assert_eq!(argument_at("tabmove 1  2", 9), Some(Argument { end: 9, index: 0, start: 8 }));
+    assert_eq!(argument_at("tabmove 1  2", 10), Some(Argument { end: 10, index: 1, start: 10 }));
+    assert_eq!(argument_at("tabmove 1  2", 12), Some(Argument { end: 12, index: 1, start: 11 }));
+    assert_eq!(argument_at("é é é", 4), Some(Argument { end: 8, index: 1, start: 6 }));
+}
+
+#[test]
+fn test_argument_completer() {
+    let mut completion = Completion::new();
+    completion.add_argument_completers(vec![("nmap".into(), vec!["keys", "action"])]);
+    assert_eq!(completion.argument_completer("nmap", 2), None);
+    assert_eq!(completion.argument_completer("nmap a", 6), Some("keys"));
+    assert_eq!(completion.argument_completer("nmap a open", 11), Some("action"));
+    assert_eq!(completion.argument_completer("nmap a open url", 15), None);
+    assert_eq!(completion.argument_completer("open url", 8), None);
+    // The options are not counted as arguments.
+    assert_eq!(completion.argument_completer("nmap --context=entry", 20), None);
+    assert_eq!(completion.argument_completer("nmap --context=entry a", 22), Some("keys"));
+    assert_eq!(completion.argument_completer("nmap --context=entry a open", 27), Some("action"));
+}
+
+struct ListCompleter {
+    deleted: Rc<RefCell<Vec<Vec<String>>>>,
+}
+
+impl Completer for ListCompleter {
+    fn completions(&mut self, input: &str) -> Vec<CompletionResult> {
+        fuzzy_completions(input, vec![vec!["open", "Open the url"], vec!["quit", "Quit the application"]])
+    }
+
+    fn delete(&mut self, row: &[String]) {
+        self.deleted.borrow_mut().push(row.to_vec());
+    }
+}
+
+#[test]
+fn test_delete_row() {
+    gtk::init().unwrap();
+    let deleted = Rc::new(RefCell::new(vec![]));
+    let mut completers: Completers = HashMap::new();
+    completers.insert("list", Box::new(ListCompleter { deleted: deleted.clone() }));
+    let mut completion = Completion::new();
+    completion.set_completers(completers);
+    completion.adjust_model("list");
+    let model = completion.filter("").unwrap();
+    let iter = model.iter_first().unwrap();
+    completion.delete_row(&iter);
+    assert_eq!(*deleted.borrow(), vec![vec!["open".to_string(), "Open the url".to_string()]]);
+    assert_eq!(model.iter_n_children(None), 1);
+    let iter = model.iter_first().unwrap();
+    assert_eq!(model.value(&iter, 0).get::<String>().unwrap(), "quit");
+}
+
+#[test]
+fn test_fuzzy_match() {
+    assert_eq!(fuzzy_match("", "open"), Some(FuzzyMatch { indices: vec![], score: 0 }));
+    assert_eq!(fuzzy_match("OP", "open").map(|fuzzy_match| fuzzy_match.indices), Some(vec![0, 1]));
+    assert_eq!(fuzzy_match("wo", "win-open").map(|fuzzy_match| fuzzy_match.indices), Some(vec![0, 4]));
+    assert_eq!(fuzzy_match("po", "open"), None);
+    assert_eq!(fuzzy_match("opens", "open"), None);
+}
+
+#[test]
+fn test_fuzzy_ranking() {
+    let start = fuzzy_match("op", "open").unwrap().score;
+    let word_start = fuzzy_match("op", "win-open").unwrap().score;
+    let scattered = fuzzy_match("op", "forward-stop").unwrap().score;
+    assert!(start > word_start);
+    assert!(word_start > scattered);
+
+    let rows = vec![
+        vec!["win-open", "Open the url in a new window"],
+        vec!["quit", "Quit the application"],
+        vec!["open", "Open the url"],
+    ];
+    let results: Vec<_> = fuzzy_completions("open", rows).into_iter()
+        .map(|result| result.columns[0].value.clone())
+        .collect();
+    assert_eq!(results, vec!["open", "win-open"]);
+}
+
+#[test]
+fn test_frecency() {
+    let path = env::temp_dir().join(format!("mg-test-frecency-{}", process::id()));
+    let _ = fs::remove_file(&path);
+    let values = |results: &[CompletionResult]| -> Vec<String> {
+        results.iter().map(|result| result.columns[0].value.clone()).collect()
+    };
+
+    let mut frecency = Frecency::new(&path).unwrap();
+    let mut results = vec![CompletionResult::new(&["open"]), CompletionResult::new(&["quit"]),
+        CompletionResult::new(&["reload"])];
+    frecency.sort(&mut results, 0);
+    assert_eq!(values(&results), vec!["open", "quit", "reload"]);
+
+    frecency.record("reload").unwrap();
+    frecency.record("reload").unwrap();
+    frecency.record("quit").unwrap();
+    assert_eq!(frecency.score("open"), 0);
+    assert!(frecency.score("reload") > frecency.score("quit"));
+
+    // The history is loaded back from the file.
+    let frecency = Frecency::new(&path).unwrap();
+    frecency.sort(&mut results, 0);
+    assert_eq!(values(&results), vec!["reload", "quit", "open"]);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_mapping_completer() {
+    let mut completer = MappingCompleter::new(vec![
+        ("<C-o>".to_string(), "open".to_string()),
+        ("q".to_string(), "quit".to_string()),
+    ]).command("nunmap");
+    let results: Vec<_> = completer.completions(" qui").into_iter()
+        .map(|result| result.columns[1].value.clone())
+        .collect();
+    assert_eq!(results, vec!["quit"]);
+    assert_eq!(completer.complete_result("<C-o>"), "nunmap <C-o>");
+}
+
+#[derive(Default, Settings)]
+pub struct AppSettings {
+    boolean: bool,
+}
+
+#[test]
+fn test_path_completer_home() {
+    let home = env::temp_dir().join(format!("mg-test-home-{}", process::id()));
+    fs::create_dir_all(home.join("documents")).unwrap();
+    fs::write(home.join("notes"), "").unwrap();
+    env::set_var("HOME", &home);
+    let mut completer = PathCompleter::new();
+    let values: Vec<_> = completer.completions("~/d").into_iter()
+        .map(|result| result.columns[0].value.clone())
+        .collect();
+    assert_eq!(values, vec!["~/documents/"]);
+    assert_eq!(common_prefix_extension("~/d", values.iter().map(String::as_str)), Some("~/documents/"));
+    let values: Vec<_> = completer.completions("~").into_iter()
+        .map(|result| result.columns[0].value.clone())
+        .collect();
+    assert_eq!(values, vec!["~/documents/", "~/notes"]);
+    fs::remove_dir_all(&home).unwrap();
+}
+
+#[test]
+fn test_setting_value_completer() {
+    let mut current_values = HashMap::new();
+    current_values.insert("boolean".to_string(), "false".to_string());
+    let mut completer = SettingCompleter::<AppSettings>::new()
+        .types()
+        .current_values(current_values);
+    let values = |results: Vec<CompletionResult>| -> Vec<(String, String)> {
+        results.into_iter()
+            .map(|result| (result.columns[0].value.clone(), result.columns[1].value.clone()))
+            .collect()
+    };
+    assert_eq!(values(completer.completions("boolean = ")), vec![
+        ("true".to_string(), String::new()),
+        ("false".to_string(), "current value".to_string()),
+    ]);
+    // The state of the value is not matched.
+    assert_eq!(values(completer.completions("boolean = t")), vec![("true".to_string(), String::new())]);
+    assert_eq!(completer.complete_result("true"), "set boolean = true");
+}
+
+#[test]
+fn test_common_prefix() {
+    assert_eq!(common_prefix("win-open", "win-close"), "win-");
+    assert_eq!(common_prefix("open", "open"), "open");
+    assert_eq!(common_prefix("open", "opened"), "open");
+    assert_eq!(common_prefix("open", "quit"), "");
+    assert_eq!(common_prefix("été", "étai"), "ét");
+    assert_eq!(common_prefix_extension("w", vec!["win-open", "win-close"]), Some("win-"));
+    assert_eq!(common_prefix_extension("W", vec!["win-open", "win-close"]), Some("win-"));
+    assert_eq!(common_prefix_extension("win-", vec!["win-open", "win-close"]), None);
+    assert_eq!(common_prefix_extension("o", vec!["open", "win-open", "quit"]), Some("open"));
+}
+
+#[test]
+fn test_common_prefix_fuzzy_results() {
+    // The fuzzy matches which do not start with the input must not replace it.
+    assert_eq!(common_prefix_extension("wo", vec!["win-open", "win-close"]), None);
+    assert_eq!(common_prefix_extension("wo", vec!["win-open", "word-count", "word-wrap"]), Some("word-"));
+}